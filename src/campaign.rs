@@ -0,0 +1,124 @@
+use crate::darkness::DarknessPool;
+use crate::faction::Party;
+use crate::journal::Journal;
+use crate::{storage, write_db, Error, CHARACTER_DB, DARKNESS_DB, JOURNAL_DB, PARTY_DB, SHIP_DB};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tui::{
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, BorderType, Borders, List, ListItem},
+};
+
+pub const CAMPAIGN_DIR: &str = "./campaigns";
+
+//Tables every campaign keeps for itself, everything else is shared from ./data
+pub const CAMPAIGN_FILES: [&str; 5] = [CHARACTER_DB, SHIP_DB, JOURNAL_DB, PARTY_DB, DARKNESS_DB];
+
+pub fn is_campaign_table(table: &str) -> bool {
+    CAMPAIGN_FILES
+        .iter()
+        .any(|f| Path::new(f).file_stem().and_then(|s| s.to_str()) == Some(table))
+}
+
+//Letters, digits, - and _ only, a campaign name is also a directory name
+fn path(name: &str) -> Result<PathBuf, Error> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(Error::InvalidCampaignName(name.to_string()));
+    }
+    Ok(Path::new(CAMPAIGN_DIR).join(name))
+}
+
+pub fn dir(name: &str) -> Result<PathBuf, Error> {
+    let dir = path(name)?;
+    if !dir.is_dir() {
+        return Err(Error::UnknownCampaign(name.to_string()));
+    }
+    Ok(dir)
+}
+
+//Campaign names in alphabetical order
+pub fn list() -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(CAMPAIGN_DIR)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect();
+    names.sort();
+    names
+}
+
+//A new campaign starts with the default characters and ship, an empty journal, no
+//reputation for the party and no Darkness Points.
+//The new campaign is left open.
+pub fn create(name: &str) -> Result<(), Error> {
+    let dir = path(name)?;
+    if dir.exists() {
+        return Err(Error::CampaignExists(name.to_string()));
+    }
+    fs::create_dir_all(dir).map_err(Error::WriteDBError)?;
+    storage::open_campaign(None)?;
+    let characters: Value = storage::load(CHARACTER_DB)?;
+    let ship: Value = storage::load(SHIP_DB)?;
+    storage::open_campaign(Some(name))?;
+    write_db(CHARACTER_DB, &characters)?;
+    write_db(SHIP_DB, &ship)?;
+    write_db(PARTY_DB, &Party::default())?;
+    write_db(DARKNESS_DB, &DarknessPool::default())?;
+    write_db(
+        JOURNAL_DB,
+        &Journal {
+            session: 1,
+            entries: Vec::new(),
+        },
+    )
+}
+
+//The first row is the shared data without a campaign
+pub fn render_campaigns<'a>(campaigns: &[String], active: Option<&str>) -> List<'a> {
+    let rows = std::iter::once(None).chain(campaigns.iter().map(|c| Some(c.as_str())));
+    let items: Vec<ListItem> = rows
+        .map(|campaign| {
+            let marker = if campaign == active { "● " } else { "  " };
+            let name = campaign.unwrap_or("Standard (data)");
+            ListItem::new(Spans::from(vec![
+                Span::styled(marker, Style::default().fg(Color::Green)),
+                Span::raw(name.to_string()),
+            ]))
+        })
+        .collect();
+    List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default().fg(Color::White))
+                .title("Kampanjer (Enter väljer)")
+                .border_type(BorderType::Plain),
+        )
+        .highlight_style(
+            Style::default()
+                .bg(Color::Yellow)
+                .fg(Color::Black)
+                .add_modifier(Modifier::BOLD),
+        )
+}
+
+#[cfg(test)]
+#[test]
+fn test_campaign_names() {
+    assert!(is_campaign_table("journal"));
+    assert!(!is_campaign_table("skills"));
+    assert!(matches!(
+        path("../rules"),
+        Err(Error::InvalidCampaignName(_))
+    ));
+    assert!(matches!(path(""), Err(Error::InvalidCampaignName(_))));
+    assert!(path("Kampanj_1").is_ok());
+}
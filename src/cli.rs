@@ -0,0 +1,463 @@
+use crate::campaign;
+use crate::darkness::DarknessPool;
+use crate::dice::roll_pool;
+use crate::encounter::EncounterTable;
+use crate::faction::{Faction, Party};
+use crate::formats;
+use crate::homebrew;
+use crate::icon::Icon;
+use crate::import::import_character;
+use crate::journal::Journal;
+use crate::lorebook::Bookmark;
+use crate::pdf::render_pdf;
+use crate::power::Powers;
+use crate::query::{self, sort_by_field};
+use crate::rpc;
+use crate::schema;
+use crate::screen::Screen;
+use crate::session::{self, Client, DEFAULT_PORT};
+use crate::sheet::{render_sheet, SheetFormat};
+use crate::ship::{Module, Ship};
+use crate::storage::{self, migrate_from_json, SQLITE_DB};
+use crate::tables::DataTable;
+use crate::travel::Horizon;
+use crate::{
+    read_armor_db, read_character_db, read_icon_db, read_item_db, read_power_db, read_skill_db,
+    read_weapon_db, write_db, Armor, Character, Item, Skill, Weapon, ARMOR_DB, BOOKMARK_DB,
+    CHARACTER_DB, DARKNESS_DB, DATA_FILES, ENCOUNTER_DB, FACTION_DB, ICON_DB, ITEM_DB, JOURNAL_DB,
+    MODULE_DB, PARTY_DB, POWER_DB, SHIP_DB, SKILL_DB, SYSTEM_DB, WEAPON_DB,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum CliError {
+    #[error("unknown command: {0}")]
+    UnknownCommand(String),
+    #[error("missing argument: {0}")]
+    MissingArgument(&'static str),
+    #[error("invalid value for {0}: {1}")]
+    InvalidValue(&'static str, String),
+    #[error("no character with id {0}")]
+    CharacterNotFound(usize),
+}
+
+const USAGE: &str = "Usage:
+    coriolis_beyond [--campaign <name>] [--gm]  start the terminal UI, --gm for the GM screen
+    Every command also takes --campaign <name> to use that campaign's files
+    coriolis_beyond export-sheet --id <id> [--format md|html|txt|pdf] [--out <file>]
+    coriolis_beyond import-character <file> [--dry-run]
+    coriolis_beyond export-csv <weapons|armor|items|skills> [--out <file>]
+    coriolis_beyond import-csv <weapons|armor|items|skills> <file> [--dry-run]
+    coriolis_beyond convert <input.json|toml|ron> <output.json|toml|ron>
+    coriolis_beyond migrate-from-json [--out <file>]
+    coriolis_beyond campaigns
+    coriolis_beyond new-campaign <name>
+    coriolis_beyond homebrew
+    coriolis_beyond serve [--port <port>]  host a session for players on the network
+    coriolis_beyond connect <host>[:<port>] [--name <name>]  the terminal UI as a player
+    coriolis_beyond rpc [--socket <path>]  JSON-RPC for chat bots on stdin/stdout or a socket
+    coriolis_beyond skill <name> [--json]
+    coriolis_beyond weapon list [--sort <field>] [--json] | weapon <name> [--json]
+    coriolis_beyond character list [--json] | character show <id|name> [--json]
+    coriolis_beyond roll <dice> [--json]
+    Add --gm to include characters only the GM sees";
+
+//Value following a --flag
+fn flag<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|a| a == name)
+        .and_then(|i| args.get(i + 1))
+        .map(|v| v.as_str())
+}
+
+fn parse_id(args: &[String]) -> Result<usize, CliError> {
+    let id = flag(args, "--id").ok_or(CliError::MissingArgument("--id"))?;
+    id.parse()
+        .map_err(|_| CliError::InvalidValue("--id", id.to_string()))
+}
+
+//Prints to stdout, or writes the file given with --out
+fn output(args: &[String], content: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+    match flag(args, "--out") {
+        Some(path) => fs::write(path, content)?,
+        //A reader such as `head` that stops early is not an error
+        None => match io::stdout().write_all(content) {
+            Err(e) if e.kind() != io::ErrorKind::BrokenPipe => return Err(e.into()),
+            _ => {}
+        },
+    }
+    Ok(())
+}
+
+fn export_sheet(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let id = parse_id(args)?;
+    let format = flag(args, "--format").unwrap_or("md");
+    let character = read_character_db()?
+        .into_iter()
+        .find(|c| c.id == id)
+        .ok_or(CliError::CharacterNotFound(id))?;
+    let (skills, weapons, armor, items, powers) = (
+        read_skill_db()?,
+        read_weapon_db()?,
+        read_armor_db()?,
+        read_item_db()?,
+        read_power_db()?.powers,
+    );
+    if format == "pdf" {
+        let pdf = render_pdf(&character, &skills, &weapons, &armor, &items, &powers)?;
+        return output(args, &pdf);
+    }
+    let format = SheetFormat::parse(format)
+        .ok_or_else(|| CliError::InvalidValue("--format", format.to_string()))?;
+    let sheet = render_sheet(
+        &character, &skills, &weapons, &armor, &items, &powers, format,
+    );
+    output(args, sheet.as_bytes())
+}
+
+//Adds a character from a Foundry VTT or generic JSON actor export
+fn import(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let json = fs::read_to_string(positional(args, 0, "<file>")?)?;
+    let mut characters = read_character_db()?;
+    let id = characters.iter().map(|c| c.id).max().unwrap_or(0) + 1;
+    let report = import_character(
+        &json,
+        id,
+        &read_skill_db()?,
+        &read_weapon_db()?,
+        &read_armor_db()?,
+        &read_item_db()?,
+        &read_icon_db()?,
+    )?;
+    for (from, to) in report.fuzzy.iter() {
+        println!("tolkade \"{}\" som \"{}\"", from, to);
+    }
+    for unmapped in report.unmapped.iter() {
+        println!("hittade ingen motsvarighet till {}", unmapped);
+    }
+    for invalid in report.invalid.iter() {
+        println!("ogiltigt värde för {}, satt till 0", invalid);
+    }
+    if args.iter().any(|a| a == "--dry-run") {
+        println!(
+            "{} (id {}) importerades inte, --dry-run",
+            report.character.name, id
+        );
+        return Ok(());
+    }
+    println!("importerade {} som id {}", report.character.name, id);
+    characters.push(report.character);
+    write_db(CHARACTER_DB, &characters)?;
+    Ok(())
+}
+
+//Positional argument that is not a --flag or a flag value
+fn positional(args: &[String], index: usize, name: &'static str) -> Result<String, CliError> {
+    args.get(index)
+        .filter(|a| !a.starts_with("--"))
+        .cloned()
+        .ok_or(CliError::MissingArgument(name))
+}
+
+fn export_csv(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let table = DataTable::parse(&positional(args, 0, "<table>")?)?;
+    output(args, table.export()?.as_bytes())
+}
+
+fn import_csv(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let table = DataTable::parse(&positional(args, 0, "<table>")?)?;
+    let data = fs::read_to_string(positional(args, 1, "<file>")?)?;
+    let dry_run = args.iter().any(|a| a == "--dry-run");
+    let summary = table.import(&data, dry_run)?;
+    println!(
+        "{} rader uppdaterade, {} nya{}",
+        summary.updated,
+        summary.added,
+        if dry_run {
+            " (--dry-run, inget sparat)"
+        } else {
+            ""
+        }
+    );
+    Ok(())
+}
+
+fn convert_as<T: Serialize + DeserializeOwned>(
+    table: &str,
+    input: &Path,
+    output: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let stored = schema::unwrap(formats::parse(input, &fs::read_to_string(input)?)?);
+    //Parsing into T checks the file before anything is written
+    let data: T = serde_json::from_value(schema::upgrade(table, stored)?)?;
+    let content = schema::wrap(serde_json::to_value(&data)?);
+    fs::write(output, formats::serialize(output, &content)?)?;
+    Ok(())
+}
+
+//The table is told by the file name, any of DATA_FILES
+fn convert_file(input: &Path, output: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let table = input
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default();
+    let data_file = DATA_FILES
+        .iter()
+        .find(|p| storage::table_name(p) == table)
+        .ok_or_else(|| CliError::InvalidValue("<input>", table.to_string()))?;
+    match *data_file {
+        SKILL_DB => convert_as::<Vec<Skill>>(table, input, output),
+        CHARACTER_DB => convert_as::<Vec<Character>>(table, input, output),
+        WEAPON_DB => convert_as::<Vec<Weapon>>(table, input, output),
+        ITEM_DB => convert_as::<Vec<Item>>(table, input, output),
+        ARMOR_DB => convert_as::<Vec<Armor>>(table, input, output),
+        SHIP_DB => convert_as::<Ship>(table, input, output),
+        MODULE_DB => convert_as::<Vec<Module>>(table, input, output),
+        SYSTEM_DB => convert_as::<Horizon>(table, input, output),
+        ENCOUNTER_DB => convert_as::<Vec<EncounterTable>>(table, input, output),
+        JOURNAL_DB => convert_as::<Journal>(table, input, output),
+        BOOKMARK_DB => convert_as::<Vec<Bookmark>>(table, input, output),
+        FACTION_DB => convert_as::<Vec<Faction>>(table, input, output),
+        PARTY_DB => convert_as::<Party>(table, input, output),
+        ICON_DB => convert_as::<Vec<Icon>>(table, input, output),
+        POWER_DB => convert_as::<Powers>(table, input, output),
+        DARKNESS_DB => convert_as::<DarknessPool>(table, input, output),
+        other => Err(CliError::InvalidValue("<input>", other.to_string()).into()),
+    }
+}
+
+//Converts a data file between JSON, TOML and RON
+fn convert(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let input = positional(args, 0, "<input>")?;
+    let output = positional(args, 1, "<output>")?;
+    convert_file(Path::new(&input), Path::new(&output))
+}
+
+fn migrate(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let target = flag(args, "--out").unwrap_or(SQLITE_DB);
+    let tables = migrate_from_json(&DATA_FILES, target)?;
+    println!("{} tabeller kopierade till {}", tables, target);
+    Ok(())
+}
+
+//Removes --campaign <name> from the arguments and opens that campaign
+pub fn open_campaign(args: &mut Vec<String>) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let i = match args.iter().position(|a| a == "--campaign") {
+        Some(i) => i,
+        None => return Ok(None),
+    };
+    if i + 1 >= args.len() {
+        return Err(CliError::MissingArgument("--campaign <name>").into());
+    }
+    let name = args.remove(i + 1);
+    args.remove(i);
+    storage::open_campaign(Some(&name))?;
+    Ok(Some(name))
+}
+
+fn serve(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let port = match flag(args, "--port") {
+        Some(port) => port
+            .parse()
+            .map_err(|_| CliError::InvalidValue("--port", port.to_string()))?,
+        None => DEFAULT_PORT,
+    };
+    session::serve(&format!("0.0.0.0:{}", port))?;
+    Ok(())
+}
+
+//Joins a session and reads every table from the host from now on
+pub fn connect(args: &[String]) -> Result<Client, Box<dyn std::error::Error>> {
+    let mut addr = positional(args, 0, "<host>")?;
+    if !addr.contains(':') {
+        addr = format!("{}:{}", addr, DEFAULT_PORT);
+    }
+    let name = flag(args, "--name").unwrap_or("Spelare");
+    let client = Client::connect(&addr, name)?;
+    storage::use_backend(Box::new(client.storage()));
+    Ok(client)
+}
+
+fn rpc(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    match flag(args, "--socket") {
+        #[cfg(unix)]
+        Some(path) => rpc::listen(path)?,
+        #[cfg(not(unix))]
+        Some(_) => {
+            return Err(CliError::InvalidValue("--socket", String::from("unix only")).into())
+        }
+        None => rpc::serve(io::stdin().lock(), io::stdout().lock())?,
+    }
+    Ok(())
+}
+
+fn wants_json(args: &[String]) -> bool {
+    args.iter().any(|a| a == "--json")
+}
+
+//JSON with --json, otherwise the text
+fn answer<T: Serialize>(
+    args: &[String],
+    value: &T,
+    text: impl FnOnce(&T) -> Result<String, Box<dyn std::error::Error>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut content = if wants_json(args) {
+        serde_json::to_string_pretty(value)?
+    } else {
+        text(value)?
+    };
+    if !content.ends_with('\n') {
+        content.push('\n');
+    }
+    output(args, content.as_bytes())
+}
+
+fn skill(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let skill = query::skill(&positional(args, 0, "<name>")?)?;
+    answer(args, &skill, |s| Ok(query::skill_text(s)))
+}
+
+fn weapon(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let name = positional(args, 0, "list|<name>")?;
+    if name != "list" {
+        let weapon = query::weapon(&name)?;
+        return answer(args, &weapon, |w| Ok(query::weapon_text(w)));
+    }
+    let mut weapons = read_weapon_db()?;
+    if let Some(field) = flag(args, "--sort") {
+        sort_by_field(&mut weapons, field, "weapon")?;
+    }
+    answer(args, &weapons, |weapons| {
+        let row = |cells: [String; 8]| {
+            format!(
+                "{:<4}{:<24}{:>6}{:>6}{:>6}{:>6}  {:<12}{:>8}\n",
+                cells[0], cells[1], cells[2], cells[3], cells[4], cells[5], cells[6], cells[7]
+            )
+        };
+        let header = [
+            "id",
+            "namn",
+            "bonus",
+            "init",
+            "skada",
+            "krit",
+            "räckvidd",
+            "kostnad",
+        ];
+        let mut table = row(header.map(String::from));
+        for w in weapons.iter() {
+            table.push_str(&row([
+                w.id.to_string(),
+                w.namn.clone(),
+                w.bonus.to_string(),
+                w.init.to_string(),
+                w.skada.to_string(),
+                w.krit.to_string(),
+                w.räckvidd.clone(),
+                w.kostnad.to_string(),
+            ]));
+        }
+        Ok(table)
+    })
+}
+
+fn character(args: &[String], screen: Screen) -> Result<(), Box<dyn std::error::Error>> {
+    match positional(args, 0, "list|show")?.as_str() {
+        "list" => answer(args, &query::characters(screen)?, |characters| {
+            Ok(characters
+                .iter()
+                .map(|c| format!("{:<4}{:<20}{}\n", c.id, c.name, c.class))
+                .collect())
+        }),
+        "show" => {
+            let character = query::character(&positional(args, 1, "<id|name>")?, screen)?;
+            answer(args, &character, |c| Ok(query::sheet_text(c)?))
+        }
+        other => Err(CliError::InvalidValue("list|show", other.to_string()).into()),
+    }
+}
+
+fn roll(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let dice = positional(args, 0, "<dice>")?;
+    let dice: u8 = dice
+        .parse()
+        .map_err(|_| CliError::InvalidValue("<dice>", dice.clone()))?;
+    let roll = roll_pool(&mut rand::thread_rng(), dice);
+    let value = serde_json::json!({
+        "dice": roll.dice,
+        "successes": roll.successes(),
+    });
+    answer(args, &value, |_| Ok(query::roll_text(&roll)))
+}
+
+fn new_campaign(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let name = positional(args, 0, "<name>")?;
+    campaign::create(&name)?;
+    println!("kampanjen {} skapad i {}", name, campaign::CAMPAIGN_DIR);
+    Ok(())
+}
+
+//Runs a subcommand given on the command line instead of the terminal UI
+pub fn run(args: &[String], screen: Screen) -> Result<(), Box<dyn std::error::Error>> {
+    match args[0].as_str() {
+        "export-sheet" => export_sheet(&args[1..]),
+        "import-character" => import(&args[1..]),
+        "export-csv" => export_csv(&args[1..]),
+        "import-csv" => import_csv(&args[1..]),
+        "convert" => convert(&args[1..]),
+        "migrate-from-json" => migrate(&args[1..]),
+        "campaigns" => {
+            campaign::list().iter().for_each(|c| println!("{}", c));
+            Ok(())
+        }
+        "new-campaign" => new_campaign(&args[1..]),
+        "serve" => serve(&args[1..]),
+        "rpc" => rpc(&args[1..]),
+        "skill" => skill(&args[1..]),
+        "weapon" => weapon(&args[1..]),
+        "character" => character(&args[1..], screen),
+        "roll" => roll(&args[1..]),
+        "homebrew" => {
+            homebrew::report()?.iter().for_each(|l| println!("{}", l));
+            Ok(())
+        }
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        other => {
+            eprintln!("{}", USAGE);
+            Err(CliError::UnknownCommand(other.to_string()).into())
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_flags() {
+    let args: Vec<String> = ["--id", "2", "--format", "html"]
+        .iter()
+        .map(|a| a.to_string())
+        .collect();
+    assert_eq!(flag(&args, "--format"), Some("html"));
+    assert_eq!(flag(&args, "--out"), None);
+    assert_eq!(parse_id(&args).unwrap(), 2);
+}
+
+#[cfg(test)]
+#[test]
+fn test_convert_every_table() {
+    let dir = std::env::temp_dir().join(format!("convert-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    for path in DATA_FILES {
+        let output = dir.join(format!("{}.ron", storage::table_name(path)));
+        convert_file(Path::new(path), &output).unwrap();
+        assert!(output.exists());
+    }
+    assert!(convert_file(Path::new("data/lore.json"), &dir.join("lore.ron")).is_err());
+    fs::remove_dir_all(&dir).unwrap();
+}
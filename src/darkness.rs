@@ -0,0 +1,107 @@
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use tui::{
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, BorderType, Borders, Paragraph, Wrap},
+};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DarknessChange {
+    pub timestamp: DateTime<Local>,
+    //Positive when the GM gains points, negative when they are spent
+    pub delta: i32,
+    pub reason: String,
+}
+
+//The GM's Darkness Points, kept per campaign
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct DarknessPool {
+    pub points: u32,
+    #[serde(default)]
+    pub history: Vec<DarknessChange>,
+}
+
+impl DarknessPool {
+    //The pool never goes below zero, the change recorded is what actually happened
+    pub fn change(&mut self, delta: i32, reason: String) -> i32 {
+        let points = (self.points as i64 + delta as i64).max(0) as u32;
+        let delta = points as i32 - self.points as i32;
+        self.points = points;
+        self.history.push(DarknessChange {
+            timestamp: Local::now(),
+            delta,
+            reason,
+        });
+        delta
+    }
+
+    //Newest first
+    pub fn recent(&self, count: usize) -> impl Iterator<Item = &DarknessChange> {
+        self.history.iter().rev().take(count)
+    }
+}
+
+//A change being typed in: the number of points and why
+pub struct DarknessInput {
+    pub delta: i32,
+    pub reason: String,
+}
+
+//The GM's dashboard. Hidden, it only says that it is hidden, for a screen the players see.
+pub fn render_darkness<'a>(
+    pool: &DarknessPool,
+    visible: bool,
+    input: Option<&DarknessInput>,
+) -> Paragraph<'a> {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .style(Style::default().fg(Color::White))
+        .title("Mörkerpoäng (+/- ändrar, d döljer)")
+        .border_type(BorderType::Plain);
+    if !visible {
+        return Paragraph::new("Dolda för spelarna").block(block);
+    }
+    let mut lines = vec![Spans::from(Span::styled(
+        format!("{} i potten", pool.points),
+        Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+    ))];
+    if let Some(input) = input {
+        lines.push(Spans::from(""));
+        lines.push(Spans::from(Span::styled(
+            format!(
+                "{:+} (Upp/Ner ändrar) anledning: {}_",
+                input.delta, input.reason
+            ),
+            Style::default().fg(Color::Yellow),
+        )));
+        lines.push(Spans::from("Enter sparar, Esc avbryter"));
+    }
+    lines.push(Spans::from(""));
+    for change in pool.recent(20) {
+        lines.push(Spans::from(vec![
+            Span::styled(
+                format!("{} {:+} ", change.timestamp.format("%H:%M"), change.delta),
+                Style::default().fg(if change.delta < 0 {
+                    Color::Green
+                } else {
+                    Color::Red
+                }),
+            ),
+            Span::raw(change.reason.clone()),
+        ]));
+    }
+    Paragraph::new(lines).wrap(Wrap { trim: true }).block(block)
+}
+
+#[cfg(test)]
+#[test]
+fn test_darkness_pool() {
+    let mut pool = DarknessPool::default();
+    assert_eq!(pool.change(2, String::from("Telekinesi")), 2);
+    assert_eq!(pool.change(-5, String::from("Bakhåll")), -2);
+    assert_eq!(pool.points, 0);
+    assert_eq!(pool.history.len(), 2);
+    let recent: Vec<&str> = pool.recent(5).map(|c| c.reason.as_str()).collect();
+    assert_eq!(recent, vec!["Bakhåll", "Telekinesi"]);
+}
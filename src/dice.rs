@@ -0,0 +1,47 @@
+use rand::Rng;
+
+//Every six on a die counts as a success (Coriolis core rules)
+pub const SUCCESS_FACE: u8 = 6;
+
+//Larger pools are a typo, not a roll
+pub const MAX_POOL: u8 = 30;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Roll {
+    pub dice: Vec<u8>,
+}
+
+impl Roll {
+    pub fn successes(&self) -> usize {
+        self.dice.iter().filter(|d| **d == SUCCESS_FACE).count()
+    }
+}
+
+//Rolls a pool of six sided dice
+pub fn roll_pool<R: Rng>(rng: &mut R, pool: u8) -> Roll {
+    Roll {
+        dice: (0..pool).map(|_| rng.gen_range(1..=6)).collect(),
+    }
+}
+
+//Dice pool for a skill roll: attribute + skill. Qualified skills (kvalificerade)
+//can only be rolled by characters that have trained them.
+pub fn skill_pool(attribute: u8, skill: u8, qualified: bool) -> u8 {
+    if qualified && skill == 0 {
+        0
+    } else {
+        attribute + skill
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_roll_pool() {
+    use rand::{rngs::StdRng, SeedableRng};
+    let mut rng = StdRng::seed_from_u64(7);
+    let roll = roll_pool(&mut rng, 8);
+    assert_eq!(roll.dice.len(), 8);
+    assert!(roll.dice.iter().all(|d| (1..=6).contains(d)));
+    assert_eq!(skill_pool(4, 0, true), 0);
+    assert_eq!(skill_pool(4, 0, false), 4);
+}
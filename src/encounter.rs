@@ -0,0 +1,215 @@
+use crate::centered_rect;
+use crate::screen::{Screen, HIDDEN};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use tui::{
+    backend::Backend,
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, BorderType, Borders, Clear, List, ListItem, Paragraph, Wrap},
+    Frame,
+};
+
+//Guards against sub-tables that (directly or indirectly) refer to themselves
+const MAX_CHAIN: usize = 8;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct EncounterEntry {
+    pub weight: u32,
+    pub text: String,
+    #[serde(default)]
+    pub table: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct EncounterTable {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub sub_table: bool,
+    pub entries: Vec<EncounterEntry>,
+}
+
+fn pick<'a, R: Rng>(rng: &mut R, table: &'a EncounterTable) -> Option<&'a EncounterEntry> {
+    let total: u32 = table.entries.iter().map(|e| e.weight).sum();
+    if total == 0 {
+        return None;
+    }
+    let mut roll = rng.gen_range(0..total);
+    for entry in table.entries.iter() {
+        if roll < entry.weight {
+            return Some(entry);
+        }
+        roll -= entry.weight;
+    }
+    None
+}
+
+//Rolls on a table and follows the sub-table references, one line per table
+pub fn roll_table<R: Rng>(rng: &mut R, tables: &[EncounterTable], id: &str) -> Vec<String> {
+    let mut results = Vec::new();
+    let mut next = Some(id.to_string());
+    while let Some(id) = next.take() {
+        if results.len() >= MAX_CHAIN {
+            break;
+        }
+        let table = match tables.iter().find(|t| t.id == id) {
+            Some(table) => table,
+            None => break,
+        };
+        if let Some(entry) = pick(rng, table) {
+            results.push(format!("{}: {}", table.name, entry.text));
+            next = entry.table.clone();
+        }
+    }
+    results
+}
+
+pub struct EncounterGenerator {
+    pub seed: u64,
+    rng: StdRng,
+    pub results: Vec<String>,
+    pub show_popup: bool,
+    pub pinned: Vec<String>,
+}
+
+impl EncounterGenerator {
+    pub fn new(seed: u64) -> EncounterGenerator {
+        EncounterGenerator {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+            results: Vec::new(),
+            show_popup: false,
+            pinned: Vec::new(),
+        }
+    }
+
+    //Restarts the sequence, the same seed always gives the same rolls
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    pub fn roll(&mut self, tables: &[EncounterTable], id: &str) {
+        self.results = roll_table(&mut self.rng, tables, id);
+        self.show_popup = true;
+    }
+
+    pub fn pin(&mut self) -> Option<String> {
+        if self.results.is_empty() {
+            return None;
+        }
+        let pinned = self.results.join(" / ");
+        self.pinned.push(pinned.clone());
+        Some(pinned)
+    }
+}
+
+pub fn root_tables(tables: &[EncounterTable]) -> Vec<&EncounterTable> {
+    tables.iter().filter(|t| !t.sub_table).collect()
+}
+
+pub fn render_encounters<'a>(
+    tables: &[EncounterTable],
+    generator: &EncounterGenerator,
+    screen: Screen,
+) -> (List<'a>, Paragraph<'a>) {
+    let items: Vec<_> = root_tables(tables)
+        .iter()
+        .map(|table| {
+            ListItem::new(Spans::from(vec![Span::styled(
+                table.name.clone(),
+                Style::default(),
+            )]))
+        })
+        .collect();
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default().fg(Color::White))
+                .title("Mötestabeller")
+                .border_type(BorderType::Plain),
+        )
+        .highlight_style(
+            Style::default()
+                .bg(Color::Yellow)
+                .fg(Color::Black)
+                .add_modifier(Modifier::BOLD),
+        );
+
+    let mut lines = vec![
+        Spans::from(format!("Seed: {}", generator.seed)),
+        Spans::from(""),
+        Spans::from(Span::styled(
+            "Fästa slag",
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+    ];
+    for pinned in generator.pinned.iter() {
+        lines.push(Spans::from(pinned.clone()));
+    }
+    //The rolls are the GM's, the players only see the tables
+    if screen == Screen::Player {
+        lines = vec![Spans::from(HIDDEN)];
+    }
+    let detail = Paragraph::new(lines).wrap(Wrap { trim: true }).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::White))
+            .title("Slumpgenerator")
+            .border_type(BorderType::Plain),
+    );
+
+    (list, detail)
+}
+
+pub fn render_encounter_popup<B: Backend>(rect: &mut Frame<B>, results: &[String]) {
+    let size = rect.size();
+    let style = Style::default()
+        .fg(Color::Yellow)
+        .add_modifier(Modifier::BOLD);
+    let span = Span::styled("Möte", style);
+    let block = Block::default().title(span).borders(Borders::ALL);
+    let lines: Vec<Spans> = results
+        .iter()
+        .map(|r| Spans::from(Span::raw(r.clone())))
+        .collect();
+    let pop_up = Paragraph::new(lines).wrap(Wrap { trim: true }).block(block);
+
+    let area = centered_rect(64, 36, size);
+    rect.render_widget(Clear, area);
+    rect.render_widget(pop_up, area);
+}
+
+#[cfg(test)]
+#[test]
+fn test_roll_table_seeded() {
+    let entry = |weight, text: &str, table: Option<&str>| EncounterEntry {
+        weight,
+        text: text.to_string(),
+        table: table.map(|t| t.to_string()),
+    };
+    let tables = vec![
+        EncounterTable {
+            id: String::from("a"),
+            name: String::from("A"),
+            sub_table: false,
+            entries: vec![entry(1, "till b", Some("b")), entry(0, "aldrig", None)],
+        },
+        EncounterTable {
+            id: String::from("b"),
+            name: String::from("B"),
+            sub_table: true,
+            entries: vec![entry(1, "igen", Some("b"))],
+        },
+    ];
+    let first = roll_table(&mut StdRng::seed_from_u64(3), &tables, "a");
+    assert_eq!(first.len(), MAX_CHAIN);
+    assert_eq!(first[0], "A: till b");
+    assert_eq!(
+        first,
+        roll_table(&mut StdRng::seed_from_u64(3), &tables, "a")
+    );
+    assert!(roll_table(&mut StdRng::seed_from_u64(3), &tables, "x").is_empty());
+}
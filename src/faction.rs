@@ -0,0 +1,237 @@
+use crate::journal::{EntryKind, Journal};
+use crate::screen::Screen;
+use crate::Character;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tui::{
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, BorderType, Borders, List, ListItem, Paragraph, Wrap},
+};
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Debug)]
+pub enum Affiliation {
+    Zenitier,
+    Förstkomna,
+    Ingen,
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Debug)]
+pub enum Attitude {
+    Allierad,
+    Handelspartner,
+    Rival,
+    Fiende,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Relationship {
+    pub faction_id: usize,
+    pub attitude: Attitude,
+    //Secret alliances and feuds the players have not found out about
+    #[serde(default)]
+    pub gm_only: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Faction {
+    pub id: usize,
+    pub name: String,
+    pub description: String,
+    pub affiliation: Affiliation,
+    #[serde(default)]
+    pub relationships: Vec<Relationship>,
+}
+
+//Standing by faction id, factions without an entry are neutral
+pub type Reputation = BTreeMap<usize, i8>;
+
+pub const MIN_STANDING: i8 = -3;
+pub const MAX_STANDING: i8 = 3;
+
+pub fn standing_label(standing: i8) -> &'static str {
+    match standing {
+        i8::MIN..=-3 => "Fiende",
+        -2 => "Fientlig",
+        -1 => "Misstänksam",
+        0 => "Neutral",
+        1 => "Vänligt inställd",
+        2 => "Allierad",
+        _ => "Hängiven",
+    }
+}
+
+//Changes a standing within MIN_STANDING..=MAX_STANDING and returns the new value
+pub fn adjust(reputation: &mut Reputation, faction_id: usize, delta: i8) -> i8 {
+    let standing = reputation.entry(faction_id).or_insert(0);
+    *standing = standing
+        .saturating_add(delta)
+        .clamp(MIN_STANDING, MAX_STANDING);
+    *standing
+}
+
+//What the group as a whole has, stored next to the characters
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct Party {
+    #[serde(default)]
+    pub reputation: Reputation,
+}
+
+//Whose reputation +/- changes: 0 is the party, n is the nth character
+pub fn target_name(characters: &[Character], target: usize) -> String {
+    match target.checked_sub(1).and_then(|i| characters.get(i)) {
+        Some(character) => character.name.clone(),
+        None => String::from("Gruppen"),
+    }
+}
+
+//Records the change in the journal, the faction tab shows it from there
+pub fn record_change(journal: &mut Journal, who: &str, faction: &Faction, standing: i8) {
+    journal.record(
+        EntryKind::Reputation,
+        format!(
+            "{} hos {}: {:+} {}",
+            who,
+            faction.name,
+            standing,
+            standing_label(standing)
+        ),
+    );
+}
+
+fn standing_span<'a>(standing: i8) -> Span<'a> {
+    let color = match standing {
+        s if s < 0 => Color::Red,
+        0 => Color::White,
+        _ => Color::Green,
+    };
+    Span::styled(
+        format!("{:+} {}", standing, standing_label(standing)),
+        Style::default().fg(color),
+    )
+}
+
+pub fn render_factions<'a>(
+    factions: &[Faction],
+    selected: Option<usize>,
+    party: &Party,
+    characters: &[Character],
+    target: usize,
+    journal: &Journal,
+    screen: Screen,
+) -> (List<'a>, Paragraph<'a>) {
+    let items: Vec<ListItem> = factions
+        .iter()
+        .map(|f| ListItem::new(Spans::from(Span::raw(f.name.clone()))))
+        .collect();
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default().fg(Color::White))
+                .title("Fraktioner")
+                .border_type(BorderType::Plain),
+        )
+        .highlight_style(
+            Style::default()
+                .bg(Color::Yellow)
+                .fg(Color::Black)
+                .add_modifier(Modifier::BOLD),
+        );
+
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let mut lines = Vec::new();
+    if let Some(faction) = selected.and_then(|i| factions.get(i)) {
+        lines.push(Spans::from(Span::styled(faction.name.clone(), bold)));
+        lines.push(Spans::from(format!(
+            "Tillhörighet: {:?}",
+            faction.affiliation
+        )));
+        lines.push(Spans::from(""));
+        lines.push(Spans::from(faction.description.clone()));
+        lines.push(Spans::from(""));
+        lines.push(Spans::from(Span::styled("Relationer", bold)));
+        for relationship in faction
+            .relationships
+            .iter()
+            .filter(|r| screen.shows(r.gm_only))
+        {
+            let other = factions
+                .iter()
+                .find(|f| f.id == relationship.faction_id)
+                .map_or("?", |f| f.name.as_str());
+            lines.push(Spans::from(format!(
+                "{:?}: {}",
+                relationship.attitude, other
+            )));
+        }
+        lines.push(Spans::from(""));
+        lines.push(Spans::from(Span::styled(
+            "Rykte (Tab byter, +/- ändrar)",
+            bold,
+        )));
+        let standings = std::iter::once(&party.reputation)
+            .chain(characters.iter().map(|c| &c.reputation))
+            .enumerate();
+        for (i, reputation) in standings {
+            let marker = if i == target { "▶ " } else { "  " };
+            let standing = reputation.get(&faction.id).copied().unwrap_or(0);
+            lines.push(Spans::from(vec![
+                Span::styled(marker, Style::default().fg(Color::Yellow)),
+                Span::raw(format!("{}: ", target_name(characters, i))),
+                standing_span(standing),
+            ]));
+        }
+        lines.push(Spans::from(""));
+        lines.push(Spans::from(Span::styled("Senaste ändringar", bold)));
+        let needle = format!(" hos {}:", faction.name);
+        let changes = journal
+            .visible(screen)
+            .rev()
+            .filter(|e| e.kind == EntryKind::Reputation && e.text.contains(&needle))
+            .take(8);
+        for entry in changes {
+            lines.push(Spans::from(format!(
+                "{} {}",
+                entry.timestamp.format("%Y-%m-%d %H:%M"),
+                entry.text
+            )));
+        }
+    }
+    let detail = Paragraph::new(lines).wrap(Wrap { trim: true }).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::White))
+            .title("Detaljer")
+            .border_type(BorderType::Plain),
+    );
+    (list, detail)
+}
+
+#[cfg(test)]
+#[test]
+fn test_factions_and_reputation() {
+    let factions = crate::read_faction_db().unwrap();
+    assert_eq!(factions.len(), 11);
+    for faction in factions.iter() {
+        for relationship in faction.relationships.iter() {
+            assert!(factions.iter().any(|f| f.id == relationship.faction_id));
+        }
+    }
+
+    let mut party = Party::default();
+    assert_eq!(adjust(&mut party.reputation, 1, 2), 2);
+    assert_eq!(adjust(&mut party.reputation, 1, 5), MAX_STANDING);
+    assert_eq!(adjust(&mut party.reputation, 2, -9), MIN_STANDING);
+    assert_eq!(standing_label(MIN_STANDING), "Fiende");
+
+    let mut journal = Journal {
+        session: 1,
+        entries: Vec::new(),
+    };
+    record_change(&mut journal, "Gruppen", &factions[0], 3);
+    assert_eq!(
+        journal.entries[0].text,
+        format!("Gruppen hos {}: +3 Hängiven", factions[0].name)
+    );
+}
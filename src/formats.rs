@@ -0,0 +1,146 @@
+use crate::Error;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum DataFormat {
+    Json,
+    Toml,
+    Ron,
+}
+
+//Hand-edited TOML and RON files take precedence over the JSON file with the same name
+const SEARCH_ORDER: [DataFormat; 3] = [DataFormat::Toml, DataFormat::Ron, DataFormat::Json];
+
+impl DataFormat {
+    pub fn from_path(path: &Path) -> Option<DataFormat> {
+        match path.extension()?.to_str()? {
+            "json" => Some(DataFormat::Json),
+            "toml" => Some(DataFormat::Toml),
+            "ron" => Some(DataFormat::Ron),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            DataFormat::Json => "json",
+            DataFormat::Toml => "toml",
+            DataFormat::Ron => "ron",
+        }
+    }
+}
+
+//The file a table is read from, `./data/skills.json` may be `./data/skills.toml` on disk
+pub fn locate(path: &str) -> PathBuf {
+    let path = Path::new(path);
+    SEARCH_ORDER
+        .iter()
+        .map(|f| path.with_extension(f.extension()))
+        .find(|p| p.exists())
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+fn format_of(path: &Path) -> Result<DataFormat, Error> {
+    DataFormat::from_path(path).ok_or_else(|| Error::UnknownFormat(path.display().to_string()))
+}
+
+//TOML has no top level arrays, a list table is stored under its file name: [[skills]]
+fn toml_key(path: &Path) -> String {
+    path.file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("rows")
+        .to_string()
+}
+
+//TOML has no null either, absent keys deserialize to None
+fn strip_nulls(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            map.retain(|_, v| !v.is_null());
+            map.values_mut().for_each(strip_nulls);
+        }
+        Value::Array(values) => values.iter_mut().for_each(strip_nulls),
+        _ => {}
+    }
+}
+
+pub fn parse<T: DeserializeOwned>(path: &Path, content: &str) -> Result<T, Error> {
+    match format_of(path)? {
+        DataFormat::Json => Ok(serde_json::from_str(content)?),
+        DataFormat::Ron => Ok(ron::from_str(content)?),
+        DataFormat::Toml => {
+            let mut value: Value = toml::from_str(content)?;
+            //Any single array key, so a converted file still loads after a rename
+            if let Some(map) = value.as_object_mut().filter(|m| m.len() == 1) {
+                if let Some(rows) = map.values_mut().find(|v| v.is_array()) {
+                    value = rows.take();
+                }
+            }
+            Ok(serde_json::from_value(value)?)
+        }
+    }
+}
+
+pub fn serialize<T: Serialize>(path: &Path, content: &T) -> Result<String, Error> {
+    match format_of(path)? {
+        DataFormat::Json => {
+            //Same four space indentation as the hand-edited files
+            let mut json = Vec::new();
+            let formatter = serde_json::ser::PrettyFormatter::with_indent(b"    ");
+            let mut serializer = serde_json::Serializer::with_formatter(&mut json, formatter);
+            content.serialize(&mut serializer)?;
+            Ok(String::from_utf8_lossy(&json).into_owned())
+        }
+        DataFormat::Ron => {
+            let config = ron::ser::PrettyConfig::default().indentor("    ".to_string());
+            ron::ser::to_string_pretty(content, config)
+                .map_err(|e| Error::SerializeError(e.to_string()))
+        }
+        DataFormat::Toml => {
+            let mut value = serde_json::to_value(content)?;
+            strip_nulls(&mut value);
+            if value.is_array() {
+                value = serde_json::json!({ toml_key(path): value });
+            }
+            toml::to_string_pretty(&value).map_err(|e| Error::SerializeError(e.to_string()))
+        }
+    }
+}
+
+//Reads a table from whichever of its JSON, TOML or RON files exists
+pub fn load<T: DeserializeOwned>(path: &str) -> Result<T, Error> {
+    let path = locate(path);
+    let content = fs::read_to_string(&path)?;
+    parse(&path, &content)
+}
+
+//Writes a table back in the format it was read from
+pub fn save<T: Serialize>(path: &str, content: &T) -> Result<(), Error> {
+    let path = locate(path);
+    let content = serialize(&path, content)?;
+    fs::write(path, content).map_err(Error::WriteDBError)
+}
+
+#[cfg(test)]
+#[test]
+fn test_formats_round_trip() {
+    use crate::{read_skill_db, read_weapon_db, Skill, Weapon};
+    let skills = read_skill_db().unwrap();
+    let weapons = read_weapon_db().unwrap();
+    for extension in ["json", "toml", "ron"] {
+        let path = PathBuf::from(format!("skills.{}", extension));
+        let text = serialize(&path, &skills).unwrap();
+        let back: Vec<Skill> = parse(&path, &text).unwrap();
+        assert_eq!(back.len(), skills.len());
+        assert_eq!(back[3].description, skills[3].description);
+
+        let path = PathBuf::from(format!("weapons.{}", extension));
+        let text = serialize(&path, &weapons).unwrap();
+        let back: Vec<Weapon> = parse(&path, &text).unwrap();
+        assert_eq!(back[0].räckvidd, weapons[0].räckvidd);
+    }
+    assert!(serialize(Path::new("skills.yaml"), &skills).is_err());
+}
@@ -0,0 +1,356 @@
+use crate::{formats, storage, Error};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+use tui::{
+    style::{Color, Style},
+    text::Span,
+};
+
+//Every JSON, TOML or RON file here is an overlay, applied in file name order
+pub const HOMEBREW_DIR: &str = "./data/homebrew";
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct Operations {
+    //Complete new entries
+    pub add: Vec<Value>,
+    //An id plus the fields to change
+    pub patch: Vec<Value>,
+    pub remove: Vec<usize>,
+}
+
+impl Operations {
+    //Every id the overlay touches
+    fn ids(&self) -> BTreeSet<usize> {
+        let id = |v: &Value| v.get("id").and_then(|id| id.as_u64()).map(|id| id as usize);
+        self.add
+            .iter()
+            .chain(self.patch.iter())
+            .filter_map(id)
+            .chain(self.remove.iter().copied())
+            .collect()
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct Overlay {
+    //Shown as the source marker, defaults to the file name
+    pub name: String,
+    pub skills: Operations,
+    pub weapons: Operations,
+    pub armor: Operations,
+    pub items: Operations,
+}
+
+impl Overlay {
+    fn operations(&self, table: &str) -> Option<&Operations> {
+        match table {
+            "skills" => Some(&self.skills),
+            "weapons" => Some(&self.weapons),
+            "armor" => Some(&self.armor),
+            "items" => Some(&self.items),
+            _ => None,
+        }
+    }
+}
+
+pub fn load_overlays(dir: &Path) -> Result<Vec<Overlay>, Error> {
+    let mut paths: Vec<_> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| formats::DataFormat::from_path(path).is_some())
+        .collect();
+    paths.sort();
+    let mut overlays = Vec::new();
+    for path in paths {
+        let mut overlay: Overlay = formats::parse(&path, &fs::read_to_string(&path)?)?;
+        if overlay.name.is_empty() {
+            overlay.name = path
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default();
+        }
+        overlays.push(overlay);
+    }
+    Ok(overlays)
+}
+
+fn position(rows: &[Value], id: usize) -> Option<usize> {
+    rows.iter()
+        .position(|r| r.get("id").and_then(|v| v.as_u64()) == Some(id as u64))
+}
+
+//The skill list uses a row named like its category as the heading of the rows after it
+fn is_heading(row: &Value) -> bool {
+    row.get("category").is_some() && row.get("category") == row.get("name")
+}
+
+//Right after the last row of the same category, so grouped lists stay grouped
+fn insert_position(rows: &[Value], entry: &Value) -> usize {
+    match entry.get("category") {
+        Some(category) => rows
+            .iter()
+            .rposition(|r| r.get("category") == Some(category))
+            .map_or(rows.len(), |i| i + 1),
+        None => rows.len(),
+    }
+}
+
+//Applies the overlays in order and marks each added or patched entry with its source.
+//Returns what could not be applied, such as a patch for an id that does not exist.
+pub fn apply(table: &str, rows: &mut Vec<Value>, overlays: &[Overlay]) -> Vec<String> {
+    let mut problems = Vec::new();
+    for overlay in overlays {
+        let ops = match overlay.operations(table) {
+            Some(ops) => ops,
+            None => continue,
+        };
+        let source = Value::from(overlay.name.clone());
+        for id in ops.remove.iter() {
+            match position(rows, *id) {
+                Some(i) if is_heading(&rows[i]) => problems.push(format!(
+                    "{}: {} id {} är en kategorirubrik",
+                    overlay.name, table, id
+                )),
+                Some(i) => {
+                    rows.remove(i);
+                }
+                None => problems.push(format!("{}: {} id {} finns inte", overlay.name, table, id)),
+            }
+        }
+        for patch in ops.patch.iter() {
+            let id = patch.get("id").and_then(|v| v.as_u64()).unwrap_or(0) as usize;
+            match (position(rows, id), patch.as_object()) {
+                (Some(i), Some(fields)) => {
+                    if let Some(row) = rows[i].as_object_mut() {
+                        row.extend(fields.clone());
+                        row.insert(String::from("source"), source.clone());
+                    }
+                }
+                _ => problems.push(format!("{}: {} id {} finns inte", overlay.name, table, id)),
+            }
+        }
+        for entry in ops.add.iter() {
+            let id = entry.get("id").and_then(|v| v.as_u64()).unwrap_or(0) as usize;
+            if position(rows, id).is_some() {
+                problems.push(format!(
+                    "{}: {} id {} finns redan, använd patch",
+                    overlay.name, table, id
+                ));
+                continue;
+            }
+            let mut entry = entry.clone();
+            entry["source"] = source.clone();
+            rows.insert(insert_position(rows, &entry), entry);
+        }
+    }
+    problems
+}
+
+//Ids that more than one overlay adds, patches or removes. The last overlay wins.
+pub fn conflicts(overlays: &[Overlay]) -> Vec<String> {
+    let mut conflicts = Vec::new();
+    for table in ["skills", "weapons", "armor", "items"] {
+        let mut touched: BTreeMap<usize, Vec<&str>> = BTreeMap::new();
+        for overlay in overlays {
+            let ids = overlay
+                .operations(table)
+                .map(|o| o.ids())
+                .unwrap_or_default();
+            for id in ids {
+                touched.entry(id).or_default().push(&overlay.name);
+            }
+        }
+        for (id, names) in touched.iter().filter(|(_, names)| names.len() > 1) {
+            conflicts.push(format!("{} id {}: {}", table, id, names.join(", ")));
+        }
+    }
+    conflicts
+}
+
+//Rows an overlay broke, for example an add without a required field, are left out and
+//reported. A broken row from the data file itself is still an error.
+fn parse_rows<T: DeserializeOwned>(
+    table: &str,
+    rows: Vec<Value>,
+) -> Result<(Vec<T>, Vec<String>), Error> {
+    let mut parsed = Vec::new();
+    let mut problems = Vec::new();
+    for row in rows {
+        let source = row.get("source").and_then(|s| s.as_str()).map(String::from);
+        let id = row.get("id").and_then(|v| v.as_u64()).unwrap_or(0);
+        match (serde_json::from_value(row), source) {
+            (Ok(entry), _) => parsed.push(entry),
+            (Err(e), Some(source)) => {
+                problems.push(format!("{}: {} id {} är ogiltig: {}", source, table, id, e))
+            }
+            (Err(e), None) => return Err(e.into()),
+        }
+    }
+    Ok((parsed, problems))
+}
+
+//A rules table with the overlays applied, plus what could not be applied
+fn load_table<T: DeserializeOwned>(
+    path: &str,
+    overlays: &[Overlay],
+) -> Result<(Vec<T>, Vec<String>), Error> {
+    let mut rows: Vec<Value> = storage::load(path)?;
+    let table = storage::table_name(path);
+    let mut problems = apply(&table, &mut rows, overlays);
+    let (parsed, invalid) = parse_rows(&table, rows)?;
+    problems.extend(invalid);
+    Ok((parsed, problems))
+}
+
+//Reads a rules table with the homebrew overlays applied
+pub fn load<T: DeserializeOwned>(path: &str) -> Result<Vec<T>, Error> {
+    let overlays = load_overlays(Path::new(HOMEBREW_DIR))?;
+    Ok(load_table(path, &overlays)?.0)
+}
+
+//Overlays, conflicts and problems, one line each
+pub fn report() -> Result<Vec<String>, Error> {
+    let overlays = load_overlays(Path::new(HOMEBREW_DIR))?;
+    let mut lines: Vec<String> = overlays
+        .iter()
+        .map(|o| format!("overlay: {}", o.name))
+        .collect();
+    lines.extend(
+        conflicts(&overlays)
+            .into_iter()
+            .map(|c| format!("konflikt: {}", c)),
+    );
+    let problems = [
+        load_table::<crate::Skill>(crate::SKILL_DB, &overlays)?.1,
+        load_table::<crate::Weapon>(crate::WEAPON_DB, &overlays)?.1,
+        load_table::<crate::Armor>(crate::ARMOR_DB, &overlays)?.1,
+        load_table::<crate::Item>(crate::ITEM_DB, &overlays)?.1,
+    ];
+    lines.extend(problems.concat().into_iter().map(|p| format!("fel: {}", p)));
+    Ok(lines)
+}
+
+//Marker after the name of entries that come from an overlay
+pub fn source_span<'a>(source: &Option<String>) -> Span<'a> {
+    match source {
+        Some(source) => Span::styled(
+            format!(" [{}]", source),
+            Style::default().fg(Color::Magenta),
+        ),
+        None => Span::raw(""),
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_apply_overlays() {
+    use serde_json::json;
+    let overlay = |name: &str, ops: Value| Overlay {
+        name: name.to_string(),
+        weapons: serde_json::from_value(ops).unwrap(),
+        ..Default::default()
+    };
+    let overlays = vec![
+        overlay(
+            "husregler",
+            json!({
+                "add": [{"id": 9, "namn": "Kaststjärna"}],
+                "patch": [{"id": 1, "skada": 3}],
+                "remove": [2]
+            }),
+        ),
+        overlay(
+            "kampanj",
+            json!({"patch": [{"id": 1, "krit": 1}, {"id": 5}]}),
+        ),
+    ];
+    let mut rows = vec![
+        json!({"id": 1, "namn": "Vulkansyrsa", "skada": 2, "krit": 2}),
+        json!({"id": 2, "namn": "Vulkan PFV"}),
+    ];
+    let problems = apply("weapons", &mut rows, &overlays);
+    assert_eq!(problems, vec!["kampanj: weapons id 5 finns inte"]);
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0]["skada"], 3);
+    assert_eq!(rows[0]["krit"], 1);
+    assert_eq!(rows[0]["source"], "kampanj");
+    assert_eq!(rows[1]["source"], "husregler");
+    assert_eq!(
+        conflicts(&overlays),
+        vec!["weapons id 1: husregler, kampanj"]
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn test_overlay_does_not_conflict_with_itself() {
+    use serde_json::json;
+    let overlays = vec![Overlay {
+        name: String::from("husregler"),
+        weapons: serde_json::from_value(json!({
+            "patch": [{"id": 1, "skada": 3}, {"id": 2}, {"id": 1, "krit": 1}],
+            "remove": [2]
+        }))
+        .unwrap(),
+        ..Default::default()
+    }];
+    assert!(conflicts(&overlays).is_empty());
+}
+
+#[cfg(test)]
+#[test]
+fn test_invalid_rows_are_skipped() {
+    use serde_json::json;
+    let overlays = vec![Overlay {
+        name: String::from("husregler"),
+        weapons: serde_json::from_value(json!({"add": [{"id": 900, "namn": "Kaststjärna"}]}))
+            .unwrap(),
+        ..Default::default()
+    }];
+    let mut rows: Vec<Value> = storage::load(crate::WEAPON_DB).unwrap();
+    let count = rows.len();
+    assert!(apply("weapons", &mut rows, &overlays).is_empty());
+    let (weapons, problems) = parse_rows::<crate::Weapon>("weapons", rows).unwrap();
+    assert_eq!(weapons.len(), count);
+    assert_eq!(problems.len(), 1);
+    assert!(problems[0].starts_with("husregler: weapons id 900 är ogiltig"));
+    //Without an overlay to blame the table is broken
+    assert!(parse_rows::<crate::Weapon>("weapons", vec![json!({"id": 1})]).is_err());
+}
+
+#[cfg(test)]
+#[test]
+fn test_added_skills_stay_in_their_category() {
+    use serde_json::json;
+    let overlays = vec![Overlay {
+        name: String::from("husregler"),
+        skills: serde_json::from_value(json!({
+            "add": [{"id": 90, "name": "Rymdråtta", "category": "Allmänna talanger"}],
+            "remove": [0, 2]
+        }))
+        .unwrap(),
+        ..Default::default()
+    }];
+    let skill = |id: usize, name: &str, category: &str| {
+        json!({"id": id, "name": name, "category": category})
+    };
+    let mut rows = vec![
+        skill(0, "Grupptalanger", "Grupptalanger"),
+        skill(1, "Näsa för Birr", "Grupptalanger"),
+        skill(2, "Snabbaste rutten", "Grupptalanger"),
+        skill(26, "Allmänna talanger", "Allmänna talanger"),
+        skill(27, "Bödel", "Allmänna talanger"),
+        skill(53, "Humanistiska talanger", "Humanistiska talanger"),
+    ];
+    let problems = apply("skills", &mut rows, &overlays);
+    assert_eq!(problems, vec!["husregler: skills id 0 är en kategorirubrik"]);
+    let ids: Vec<u64> = rows.iter().map(|r| r["id"].as_u64().unwrap()).collect();
+    assert_eq!(ids, vec![0, 1, 26, 27, 90, 53]);
+}
@@ -0,0 +1,115 @@
+use crate::{Character, Skill};
+use serde::{Deserialize, Serialize};
+use tui::{
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, BorderType, Borders, List, ListItem, Paragraph, Wrap},
+};
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Icon {
+    pub id: usize,
+    pub name: String,
+    pub domains: Vec<String>,
+    pub description: String,
+    //The icon talent in skills.json a character born under the icon gets
+    pub talent_id: usize,
+}
+
+//Character.icon holds the icon's name, compared without regard to case
+pub fn find<'a>(icons: &'a [Icon], name: &str) -> Option<&'a Icon> {
+    let name = name.trim().to_lowercase();
+    icons.iter().find(|i| i.name.to_lowercase() == name)
+}
+
+pub fn talent<'a>(icon: &Icon, skills: &'a [Skill]) -> Option<&'a Skill> {
+    skills.iter().find(|s| s.id == icon.talent_id)
+}
+
+//Characters whose icon is missing from icons.json, one line each
+pub fn validate(characters: &[Character], icons: &[Icon]) -> Vec<String> {
+    characters
+        .iter()
+        .filter(|c| find(icons, &c.icon).is_none())
+        .map(|c| format!("{}: okänd ikon \"{}\"", c.name, c.icon))
+        .collect()
+}
+
+pub fn render_icons<'a>(
+    icons: &[Icon],
+    selected: Option<usize>,
+    skills: &[Skill],
+    characters: &[Character],
+) -> (List<'a>, Paragraph<'a>) {
+    let items: Vec<ListItem> = icons
+        .iter()
+        .map(|i| ListItem::new(Spans::from(Span::raw(i.name.clone()))))
+        .collect();
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default().fg(Color::White))
+                .title("Ikoner")
+                .border_type(BorderType::Plain),
+        )
+        .highlight_style(
+            Style::default()
+                .bg(Color::Yellow)
+                .fg(Color::Black)
+                .add_modifier(Modifier::BOLD),
+        );
+
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let mut lines = Vec::new();
+    if let Some(icon) = selected.and_then(|i| icons.get(i)) {
+        lines.push(Spans::from(Span::styled(icon.name.clone(), bold)));
+        lines.push(Spans::from(format!("Domäner: {}", icon.domains.join(", "))));
+        lines.push(Spans::from(""));
+        lines.push(Spans::from(icon.description.clone()));
+        lines.push(Spans::from(""));
+        if let Some(talent) = talent(icon, skills) {
+            lines.push(Spans::from(Span::styled(talent.name.clone(), bold)));
+            lines.push(Spans::from(talent.description.clone()));
+            lines.push(Spans::from(""));
+        }
+        lines.push(Spans::from(Span::styled("Födda under ikonen", bold)));
+        for character in characters
+            .iter()
+            .filter(|c| find(icons, &c.icon).map(|i| i.id) == Some(icon.id))
+        {
+            lines.push(Spans::from(character.name.clone()));
+        }
+    }
+    let detail = Paragraph::new(lines).wrap(Wrap { trim: true }).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::White))
+            .title("Detaljer")
+            .border_type(BorderType::Plain),
+    );
+    (list, detail)
+}
+
+#[cfg(test)]
+#[test]
+fn test_icons_link_to_talents() {
+    let icons = crate::read_icon_db().unwrap();
+    let skills = crate::read_skill_db().unwrap();
+    assert_eq!(icons.len(), 9);
+    for icon in icons.iter() {
+        let talent = talent(icon, &skills).unwrap();
+        assert_eq!(talent.category, "Ikontalanger");
+    }
+    assert_eq!(find(&icons, " gråterskan").unwrap().name, "Gråterskan");
+
+    let mut characters = crate::read_character_db().unwrap();
+    characters.truncate(1);
+    characters[0].icon = String::from("krämaren");
+    assert!(validate(&characters, &icons).is_empty());
+    characters[0].icon = String::from("Nörden");
+    assert_eq!(
+        validate(&characters, &icons),
+        vec![format!("{}: okänd ikon \"Nörden\"", characters[0].name)]
+    );
+}
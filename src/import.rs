@@ -0,0 +1,410 @@
+use crate::icon::Icon;
+use crate::{Armor, Character, Fardigheter, Grundegenskaper, Item, Skill, Weapon};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+//Names closer than this (normalized Damerau-Levenshtein) count as the same entry
+const FUZZY_THRESHOLD: f64 = 0.75;
+
+//The documented generic actor format, see "Importing characters" in the README
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct GenericActor {
+    pub name: String,
+    pub experience: u8,
+    pub class: String,
+    pub ship_position: String,
+    pub problem: String,
+    pub icon: String,
+    pub background: String,
+    pub upbringing: String,
+    pub group_concept: String,
+    pub birr: u32,
+    pub face: String,
+    pub clothing: String,
+    //Checked with small_number when the character is built
+    pub attributes: BTreeMap<String, Value>,
+    pub skills: BTreeMap<String, Value>,
+    pub talents: Vec<String>,
+    pub weapons: Vec<String>,
+    pub armor: Vec<String>,
+    pub gear: Vec<String>,
+}
+
+//Foundry VTT (yzecoriolis) keys and the matching field names in this crate
+const FOUNDRY_ATTRIBUTES: [(&str, &str); 4] = [
+    ("strength", "styrka"),
+    ("agility", "kyla"),
+    ("wits", "skärpa"),
+    ("empathy", "känsla"),
+];
+
+const FOUNDRY_SKILLS: [(&str, &str); 16] = [
+    ("force", "kraftprov"),
+    ("manipulation", "manipulera"),
+    ("meleecombat", "närkamp"),
+    ("dexterity", "rörlighet"),
+    ("rangedcombat", "skjutvapen"),
+    ("infiltration", "smyga"),
+    ("observation", "spaning"),
+    ("survival", "överlevnad"),
+    ("command", "befäl"),
+    ("datadjinn", "datadjinn"),
+    ("culture", "horistonens_kultur"),
+    ("medicurgy", "medikrugi"),
+    ("mysticpowers", "mystiska_krafter"),
+    ("pilot", "pilot"),
+    ("technology", "teknologi"),
+    ("science", "vetenskap"),
+];
+
+//Foundry stores most values as {"value": x}, older exports as the bare value
+fn value(v: &Value) -> &Value {
+    v.get("value").unwrap_or(v)
+}
+
+fn text(v: Option<&Value>) -> String {
+    v.map(value)
+        .and_then(|v| v.as_str())
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn number(v: Option<&Value>) -> u64 {
+    v.map(value).and_then(|v| v.as_u64()).unwrap_or(0)
+}
+
+//Attributes and skills, the value as text when it is negative, above 255 or not a number
+fn small_number(v: Option<&Value>) -> Result<u8, String> {
+    match v.map(value) {
+        None | Some(Value::Null) => Ok(0),
+        Some(v) => v
+            .as_i64()
+            .and_then(|n| u8::try_from(n).ok())
+            .ok_or_else(|| v.to_string()),
+    }
+}
+
+fn is_foundry(actor: &Value) -> bool {
+    actor.get("items").is_some() && (actor.get("system").is_some() || actor.get("data").is_some())
+}
+
+//Maps a Foundry actor export onto the generic format
+fn from_foundry(actor: &Value, unmapped: &mut Vec<String>) -> GenericActor {
+    //Foundry v10+ uses "system", earlier versions "data"
+    let system = actor.get("system").or_else(|| actor.get("data"));
+    let field = |path: &[&str]| path.iter().try_fold(system?, |v, key| v.get(key));
+    let bio = |key: &str| text(field(&["bio", key]));
+
+    let mut generic = GenericActor {
+        name: text(actor.get("name")),
+        experience: number(field(&["experience"])).min(u8::MAX as u64) as u8,
+        class: bio("concept"),
+        ship_position: bio("crewPosition"),
+        problem: bio("personalProblem"),
+        icon: bio("icon"),
+        background: bio("origin"),
+        upbringing: bio("upbringing"),
+        group_concept: bio("groupConcept"),
+        birr: number(field(&["birr"])).min(u32::MAX as u64) as u32,
+        face: bio("face"),
+        clothing: bio("clothing"),
+        ..Default::default()
+    };
+    for (key, ours) in FOUNDRY_ATTRIBUTES {
+        if let Some(v) = field(&["attributes", key]) {
+            generic.attributes.insert(ours.to_string(), v.clone());
+        }
+    }
+    for (key, ours) in FOUNDRY_SKILLS {
+        if let Some(v) = field(&["skills", key]) {
+            generic.skills.insert(ours.to_string(), v.clone());
+        }
+    }
+
+    let items = actor.get("items").and_then(|i| i.as_array());
+    for item in items.into_iter().flatten() {
+        let name = text(item.get("name"));
+        match item
+            .get("type")
+            .and_then(|t| t.as_str())
+            .unwrap_or_default()
+        {
+            "talent" => generic.talents.push(name),
+            "weapon" => generic.weapons.push(name),
+            "armor" => generic.armor.push(name),
+            "gear" => generic.gear.push(name),
+            other => unmapped.push(format!("föremål av typen {}: {}", other, name)),
+        }
+    }
+    generic
+}
+
+fn attribute_mut<'a>(g: &'a mut Grundegenskaper, key: &str) -> Option<&'a mut u8> {
+    match key {
+        "styrka" => Some(&mut g.styrka),
+        "kyla" => Some(&mut g.kyla),
+        "skärpa" => Some(&mut g.skärpa),
+        "känsla" => Some(&mut g.känsla),
+        _ => None,
+    }
+}
+
+fn skill_mut<'a>(f: &'a mut Fardigheter, key: &str) -> Option<&'a mut u8> {
+    let (a, k) = (&mut f.allmanna, &mut f.kvalificerade);
+    match key {
+        "kraftprov" => Some(&mut a.kraftprov),
+        "manipulera" => Some(&mut a.manipulera),
+        "närkamp" => Some(&mut a.närkamp),
+        "rörlighet" => Some(&mut a.rörlighet),
+        "skjutvapen" => Some(&mut a.skjutvapen),
+        "smyga" => Some(&mut a.smyga),
+        "spaning" => Some(&mut a.spaning),
+        "överlevnad" => Some(&mut a.överlevnad),
+        "befäl" => Some(&mut k.befäl),
+        "datadjinn" => Some(&mut k.datadjinn),
+        "horistonens_kultur" => Some(&mut k.horistonens_kultur),
+        "medikrugi" => Some(&mut k.medikrugi),
+        "mystiska_krafter" => Some(&mut k.mystiska_krafter),
+        "pilot" => Some(&mut k.pilot),
+        "teknologi" => Some(&mut k.teknologi),
+        "vetenskap" => Some(&mut k.vetenskap),
+        _ => None,
+    }
+}
+
+pub struct ImportReport {
+    pub character: Character,
+    //(name in the export, name in our data) for every approximate match
+    pub fuzzy: Vec<(String, String)>,
+    pub unmapped: Vec<String>,
+    //Fields whose value is out of range, left at 0
+    pub invalid: Vec<String>,
+}
+
+//Finds the id of `name`, exact (ignoring case) first and then the closest spelling
+pub fn resolve<'a>(
+    name: &str,
+    candidates: impl Iterator<Item = (usize, &'a str)>,
+) -> Option<(usize, &'a str)> {
+    let name = name.trim().to_lowercase();
+    let mut best: Option<(f64, usize, &str)> = None;
+    for (id, candidate) in candidates {
+        let score = strsim::normalized_damerau_levenshtein(&name, &candidate.to_lowercase());
+        if best.is_none_or(|(s, _, _)| score > s) {
+            best = Some((score, id, candidate));
+        }
+    }
+    best.filter(|(score, _, _)| *score >= FUZZY_THRESHOLD)
+        .map(|(_, id, candidate)| (id, candidate))
+}
+
+fn resolve_all<'a, F, I>(
+    names: &[String],
+    kind: &str,
+    candidates: F,
+    fuzzy: &mut Vec<(String, String)>,
+    unmapped: &mut Vec<String>,
+) -> Vec<usize>
+where
+    F: Fn() -> I,
+    I: Iterator<Item = (usize, &'a str)>,
+{
+    let mut ids = Vec::new();
+    for name in names {
+        match resolve(name, candidates()) {
+            Some((id, found)) => {
+                if found.to_lowercase() != name.trim().to_lowercase() {
+                    fuzzy.push((name.clone(), found.to_string()));
+                }
+                if !ids.contains(&id) {
+                    ids.push(id);
+                }
+            }
+            None => unmapped.push(format!("{}: {}", kind, name)),
+        }
+    }
+    ids
+}
+
+//Parses a Foundry VTT or generic actor export into a character with the given id
+pub fn import_character(
+    json: &str,
+    id: usize,
+    skills: &[Skill],
+    weapons: &[Weapon],
+    armor: &[Armor],
+    items: &[Item],
+    icons: &[Icon],
+) -> Result<ImportReport, serde_json::Error> {
+    let actor: Value = serde_json::from_str(json)?;
+    let mut unmapped = Vec::new();
+    let mut fuzzy = Vec::new();
+    let mut invalid = Vec::new();
+    let generic = if is_foundry(&actor) {
+        from_foundry(&actor, &mut unmapped)
+    } else {
+        serde_json::from_value(actor)?
+    };
+
+    let mut character = Character {
+        id,
+        name: generic.name,
+        experience: generic.experience,
+        class: generic.class,
+        ship_position: generic.ship_position,
+        problem: generic.problem,
+        background: generic.background,
+        upbringing: generic.upbringing,
+        group_concept: generic.group_concept,
+        birr: generic.birr,
+        ..Default::default()
+    };
+    character.appearance.face = generic.face;
+    character.appearance.clothing = generic.clothing;
+    for (key, v) in generic.attributes.iter() {
+        match attribute_mut(&mut character.grundegenskaper, key) {
+            Some(field) => match small_number(Some(v)) {
+                Ok(v) => *field = v,
+                Err(bad) => invalid.push(format!("grundegenskap {}: {}", key, bad)),
+            },
+            None => unmapped.push(format!("grundegenskap: {}", key)),
+        }
+    }
+    let g = &character.grundegenskaper;
+    character.hit_points = g.styrka.saturating_add(g.kyla);
+    for (key, v) in generic.skills.iter() {
+        match skill_mut(&mut character.fardigheter, key) {
+            Some(field) => match small_number(Some(v)) {
+                Ok(v) => *field = v,
+                Err(bad) => invalid.push(format!("färdighet {}: {}", key, bad)),
+            },
+            None => unmapped.push(format!("färdighet: {}", key)),
+        }
+    }
+
+    if !generic.icon.trim().is_empty() {
+        let ids = resolve_all(
+            &[generic.icon],
+            "ikon",
+            || icons.iter().map(|i| (i.id, i.name.as_str())),
+            &mut fuzzy,
+            &mut unmapped,
+        );
+        if let Some(icon) = ids
+            .first()
+            .and_then(|id| icons.iter().find(|i| i.id == *id))
+        {
+            character.icon = icon.name.clone();
+        }
+    }
+    character.skill_ids = resolve_all(
+        &generic.talents,
+        "talang",
+        || skills.iter().map(|s| (s.id, s.name.as_str())),
+        &mut fuzzy,
+        &mut unmapped,
+    );
+    character.weapon_ids = resolve_all(
+        &generic.weapons,
+        "vapen",
+        || weapons.iter().map(|w| (w.id, w.namn.as_str())),
+        &mut fuzzy,
+        &mut unmapped,
+    );
+    character.armor_ids = resolve_all(
+        &generic.armor,
+        "rustning",
+        || armor.iter().map(|a| (a.id, a.name.as_str())),
+        &mut fuzzy,
+        &mut unmapped,
+    );
+    character.gear_ids = resolve_all(
+        &generic.gear,
+        "utrustning",
+        || items.iter().map(|i| (i.id, i.name.as_str())),
+        &mut fuzzy,
+        &mut unmapped,
+    );
+
+    Ok(ImportReport {
+        character,
+        fuzzy,
+        unmapped,
+        invalid,
+    })
+}
+
+#[cfg(test)]
+#[test]
+fn test_import_foundry_sample() {
+    use crate::{read_armor_db, read_icon_db, read_item_db, read_skill_db, read_weapon_db};
+    let json = std::fs::read_to_string("./data/samples/foundry_actor.json").unwrap();
+    let report = import_character(
+        &json,
+        7,
+        &read_skill_db().unwrap(),
+        &read_weapon_db().unwrap(),
+        &read_armor_db().unwrap(),
+        &read_item_db().unwrap(),
+        &read_icon_db().unwrap(),
+    )
+    .unwrap();
+    let character = &report.character;
+    assert_eq!(character.id, 7);
+    assert_eq!(character.name, "Zafira Kell");
+    assert_eq!(character.icon, "Resenären");
+    assert_eq!(character.grundegenskaper.kyla, 4);
+    assert_eq!(character.fardigheter.kvalificerade.pilot, 3);
+    assert_eq!(character.skill_ids.len(), 3);
+    assert_eq!(character.weapon_ids, vec![1]);
+    assert_eq!(character.gear_ids, vec![1]);
+    assert_eq!(
+        report.fuzzy,
+        vec![(String::from("Sprintr"), String::from("Sprinter"))]
+    );
+    assert_eq!(report.unmapped, vec!["utrustning: Holografisk tärning"]);
+    assert!(report.invalid.is_empty());
+
+    //Values that do not fit in a u8 are reported, not wrapped
+    let mut actor: Value = serde_json::from_str(&json).unwrap();
+    actor["system"]["attributes"]["strength"]["value"] = Value::from(260);
+    actor["system"]["skills"]["pilot"]["value"] = Value::from(-1);
+    let report = import_character(
+        &actor.to_string(),
+        7,
+        &read_skill_db().unwrap(),
+        &read_weapon_db().unwrap(),
+        &read_armor_db().unwrap(),
+        &read_item_db().unwrap(),
+        &read_icon_db().unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        report.invalid,
+        vec!["grundegenskap styrka: 260", "färdighet pilot: -1"]
+    );
+    assert_eq!(report.character.grundegenskaper.styrka, 0);
+    assert_eq!(report.character.fardigheter.kvalificerade.pilot, 0);
+}
+
+#[cfg(test)]
+#[test]
+fn test_import_generic_out_of_range() {
+    let json = r#"{
+        "name": "Stark",
+        "attributes": { "styrka": 200, "kyla": 100, "skärpa": 300 },
+        "skills": { "pilot": -2, "smyga": 3 }
+    }"#;
+    let report = import_character(json, 1, &[], &[], &[], &[], &[]).unwrap();
+    let character = &report.character;
+    assert_eq!(character.grundegenskaper.styrka, 200);
+    assert_eq!(character.grundegenskaper.skärpa, 0);
+    assert_eq!(character.hit_points, u8::MAX);
+    assert_eq!(character.fardigheter.allmanna.smyga, 3);
+    assert_eq!(
+        report.invalid,
+        vec!["grundegenskap skärpa: 300", "färdighet pilot: -2"]
+    );
+}
@@ -0,0 +1,204 @@
+use crate::screen::Screen;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use tui::{
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, BorderType, Borders, Paragraph, Wrap},
+};
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Debug)]
+pub enum EntryKind {
+    Note,
+    Roll,
+    Encounter,
+    Xp,
+    Purchase,
+    Darkness,
+    Reputation,
+}
+
+impl EntryKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            EntryKind::Note => "Anteckning",
+            EntryKind::Roll => "Slag",
+            EntryKind::Encounter => "Möte",
+            EntryKind::Xp => "Erfarenhet",
+            EntryKind::Purchase => "Köp",
+            EntryKind::Darkness => "Mörkerpoäng",
+            EntryKind::Reputation => "Rykte",
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            EntryKind::Note => Color::White,
+            EntryKind::Roll => Color::Yellow,
+            EntryKind::Encounter => Color::Cyan,
+            EntryKind::Xp => Color::Green,
+            EntryKind::Purchase => Color::Magenta,
+            EntryKind::Darkness => Color::Red,
+            EntryKind::Reputation => Color::Blue,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct JournalEntry {
+    pub session: u32,
+    pub timestamp: DateTime<Local>,
+    pub kind: EntryKind,
+    pub text: String,
+    //Secret notes, Darkness Points and encounter rolls are only for the GM
+    #[serde(default)]
+    pub gm_only: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Journal {
+    //The session new entries are recorded in
+    pub session: u32,
+    pub entries: Vec<JournalEntry>,
+}
+
+impl Journal {
+    pub fn record(&mut self, kind: EntryKind, text: String) {
+        self.push(kind, text, false);
+    }
+
+    //An entry the player screen leaves out
+    pub fn record_secret(&mut self, kind: EntryKind, text: String) {
+        self.push(kind, text, true);
+    }
+
+    fn push(&mut self, kind: EntryKind, text: String, gm_only: bool) {
+        self.entries.push(JournalEntry {
+            session: self.session,
+            timestamp: Local::now(),
+            kind,
+            text,
+            gm_only,
+        });
+    }
+
+    pub fn visible(&self, screen: Screen) -> impl DoubleEndedIterator<Item = &JournalEntry> {
+        self.entries.iter().filter(move |e| screen.shows(e.gm_only))
+    }
+
+    pub fn new_session(&mut self) {
+        self.session += 1;
+    }
+
+    //Session numbers in order, each listed once
+    pub fn sessions(&self) -> Vec<u32> {
+        let mut sessions: Vec<u32> = self.entries.iter().map(|e| e.session).collect();
+        sessions.sort_unstable();
+        sessions.dedup();
+        sessions
+    }
+
+    pub fn to_markdown(&self, screen: Screen) -> String {
+        let mut md = String::from("# Kampanjjournal\n");
+        for session in self.sessions() {
+            md.push_str(&format!("\n## Spelmöte {}\n\n", session));
+            for entry in self.visible(screen).filter(|e| e.session == session) {
+                md.push_str(&format!(
+                    "- **{}** _{}_: {}\n",
+                    entry.timestamp.format("%Y-%m-%d %H:%M"),
+                    entry.kind.label(),
+                    entry.text
+                ));
+            }
+        }
+        md
+    }
+}
+
+pub fn render_journal<'a>(journal: &Journal, scroll: u16, screen: Screen) -> Paragraph<'a> {
+    let mut lines = Vec::new();
+    for session in journal.sessions() {
+        lines.push(Spans::from(Span::styled(
+            format!("Spelmöte {}", session),
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        )));
+        for entry in journal.visible(screen).filter(|e| e.session == session) {
+            let text = if entry.gm_only {
+                Span::styled(entry.text.clone(), Style::default().fg(Color::DarkGray))
+            } else {
+                Span::raw(entry.text.clone())
+            };
+            lines.push(Spans::from(vec![
+                Span::styled(
+                    format!("{} ", entry.timestamp.format("%Y-%m-%d %H:%M")),
+                    Style::default().fg(Color::DarkGray),
+                ),
+                Span::styled(
+                    format!("{:<12}", entry.kind.label()),
+                    Style::default().fg(entry.kind.color()),
+                ),
+                text,
+            ]));
+        }
+        lines.push(Spans::from(""));
+    }
+    Paragraph::new(lines)
+        .wrap(Wrap { trim: false })
+        .scroll((scroll, 0))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default().fg(Color::White))
+                .title(format!("Journal - spelmöte {}", journal.session))
+                .border_type(BorderType::Plain),
+        )
+}
+
+pub fn render_journal_input<'a>(
+    input: Option<&String>,
+    secret: bool,
+    message: &str,
+) -> Paragraph<'a> {
+    let (title, text) = match input {
+        Some(buffer) if secret => (
+            "Ny hemlig anteckning (Enter sparar, Esc avbryter)",
+            buffer.clone(),
+        ),
+        Some(buffer) => ("Ny anteckning (Enter sparar, Esc avbryter)", buffer.clone()),
+        None => ("Anteckning", message.to_string()),
+    };
+    Paragraph::new(text).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::White))
+            .title(title)
+            .border_type(BorderType::Plain),
+    )
+}
+
+#[cfg(test)]
+#[test]
+fn test_journal_markdown() {
+    let mut journal = Journal {
+        session: 1,
+        entries: vec![],
+    };
+    journal.record(
+        EntryKind::Note,
+        String::from("Gruppen anländer till Coriolis"),
+    );
+    journal.new_session();
+    journal.record(EntryKind::Xp, String::from("Dakota +1 XP"));
+    journal.record(EntryKind::Purchase, String::from("Lastrum, 20000 birr"));
+    journal.record_secret(EntryKind::Note, String::from("Agenten är en förrädare"));
+    assert_eq!(journal.sessions(), vec![1, 2]);
+    let md = journal.to_markdown(Screen::Gm);
+    assert!(md.contains("## Spelmöte 1"));
+    assert!(md.contains("## Spelmöte 2"));
+    assert!(md.contains("_Erfarenhet_: Dakota +1 XP"));
+    assert!(md.contains("förrädare"));
+    assert!(!journal.to_markdown(Screen::Player).contains("förrädare"));
+    assert_eq!(journal.visible(Screen::Player).count(), 3);
+}
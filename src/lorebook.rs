@@ -0,0 +1,380 @@
+use crate::lore::LORE;
+use crate::{campaign, markdown, Error};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use tui::{
+    style::{Color, Modifier, Style},
+    text::{Span, Spans, Text},
+    widgets::{Block, BorderType, Borders, List, ListItem, Paragraph, Wrap},
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Chapter {
+    pub title: String,
+    //Rendered once when loaded, not for every frame
+    pub lines: Vec<Spans<'static>>,
+}
+
+//A saved position, by title so it survives edits to the text
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Bookmark {
+    pub chapter: String,
+    pub scroll: u16,
+}
+
+//Headings in the lore are short lines in capitals, e.g. ZENIT OCH NADIR
+fn is_heading(line: &str) -> bool {
+    line.chars().any(|c| c.is_alphabetic())
+        && line
+            .chars()
+            .filter(|c| c.is_alphabetic())
+            .all(|c| c.is_uppercase())
+        && line.chars().count() < 60
+}
+
+//Markdown files in here replace the built-in LORE, a campaign adds its own from <campaign>/lore
+pub const LORE_DIR: &str = "./data/lore";
+
+//Splits the built-in text at the headings. Indentation and repeated spaces are dropped so
+//the wrapped height can be computed exactly.
+pub fn chapters(text: &str) -> Vec<Chapter> {
+    let mut chapters: Vec<(String, String)> = Vec::new();
+    for line in text.lines() {
+        let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        if is_heading(&line) {
+            chapters.push((line, String::new()));
+            continue;
+        }
+        if chapters.is_empty() {
+            if line.is_empty() {
+                continue;
+            }
+            chapters.push((String::from("INLEDNING"), String::new()));
+        }
+        let text = &mut chapters.last_mut().expect("a chapter was just added").1;
+        if !text.is_empty() || !line.is_empty() {
+            text.push_str(&line);
+            text.push('\n');
+        }
+    }
+    chapters
+        .into_iter()
+        .map(|(title, text)| Chapter {
+            title,
+            lines: text
+                .trim_end()
+                .lines()
+                .map(|l| Spans::from(l.to_string()))
+                .collect(),
+        })
+        .collect()
+}
+
+//Every .md file in the directory in file name order, none if it does not exist
+fn markdown_chapters(dir: &Path) -> Result<Vec<Chapter>, Error> {
+    let mut paths: Vec<_> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|e| e == "md"))
+        .collect();
+    paths.sort();
+    let mut chapters = Vec::new();
+    for path in paths {
+        let name = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let text = fs::read_to_string(&path)?;
+        chapters.extend(
+            markdown::split_chapters(&name, &text)
+                .into_iter()
+                .map(|(title, body)| Chapter {
+                    title,
+                    lines: markdown::render(&body),
+                }),
+        );
+    }
+    Ok(chapters)
+}
+
+//The core lore from LORE_DIR, or the built-in text when there is none,
+//followed by the notes of the open campaign
+pub fn load(campaign: Option<&str>) -> Result<Vec<Chapter>, Error> {
+    let mut chapters = markdown_chapters(Path::new(LORE_DIR))?;
+    if chapters.is_empty() {
+        chapters = self::chapters(LORE);
+    }
+    if let Some(name) = campaign {
+        chapters.extend(markdown_chapters(&campaign::dir(name)?.join("lore"))?);
+    }
+    Ok(chapters)
+}
+
+//Rows a line takes when wrapped at word boundaries, like Paragraph with Wrap { trim: true }
+fn line_height(line: &str, width: usize) -> usize {
+    let mut rows = 1;
+    let mut current = 0;
+    for word in line.split(' ').filter(|w| !w.is_empty()) {
+        let len = word.chars().count();
+        if current > 0 && current + 1 + len <= width {
+            current += 1 + len;
+            continue;
+        }
+        if current > 0 {
+            rows += 1;
+        }
+        current = len;
+        //Words longer than a row are cut
+        while current > width {
+            rows += 1;
+            current -= width;
+        }
+    }
+    rows
+}
+
+pub fn wrapped_height(lines: &[Spans], width: u16) -> u16 {
+    let width = width.max(1) as usize;
+    lines
+        .iter()
+        .map(|l| {
+            let line: String = l.0.iter().map(|s| s.content.as_ref()).collect();
+            line_height(&line, width)
+        })
+        .sum::<usize>() as u16
+}
+
+pub struct LoreBrowser {
+    pub chapters: Vec<Chapter>,
+    pub selected: usize,
+    pub scroll: u16,
+    pub bookmarks: Vec<Bookmark>,
+    //Size of the text area inside the borders, set when it is drawn
+    width: u16,
+    height: u16,
+}
+
+impl LoreBrowser {
+    pub fn new(chapters: Vec<Chapter>, bookmarks: Vec<Bookmark>) -> LoreBrowser {
+        LoreBrowser {
+            chapters,
+            selected: 0,
+            scroll: 0,
+            bookmarks,
+            width: 80,
+            height: 20,
+        }
+    }
+
+    //After a campaign switch, bookmarks stay with their chapter titles
+    pub fn set_chapters(&mut self, chapters: Vec<Chapter>) {
+        self.chapters = chapters;
+        self.select(0);
+    }
+
+    pub fn chapter(&self) -> &Chapter {
+        &self.chapters[self.selected]
+    }
+
+    pub fn set_view(&mut self, width: u16, height: u16) {
+        self.width = width;
+        self.height = height;
+        self.scroll = self.scroll.min(self.max_scroll());
+    }
+
+    //The last row of the chapter sits at the bottom of the view
+    pub fn max_scroll(&self) -> u16 {
+        wrapped_height(&self.chapter().lines, self.width).saturating_sub(self.height)
+    }
+
+    pub fn select(&mut self, index: usize) {
+        self.selected = index % self.chapters.len();
+        self.scroll = 0;
+    }
+
+    pub fn next_chapter(&mut self) {
+        self.select(self.selected + 1);
+    }
+
+    pub fn previous_chapter(&mut self) {
+        self.select(self.selected + self.chapters.len() - 1);
+    }
+
+    pub fn scroll_down(&mut self, rows: u16) {
+        self.scroll = self.scroll.saturating_add(rows).min(self.max_scroll());
+    }
+
+    pub fn scroll_up(&mut self, rows: u16) {
+        self.scroll = self.scroll.saturating_sub(rows);
+    }
+
+    //One row of the previous page stays visible
+    pub fn page(&self) -> u16 {
+        self.height.saturating_sub(1).max(1)
+    }
+
+    fn bookmark_index(&self) -> Option<usize> {
+        let title = &self.chapter().title;
+        self.bookmarks.iter().position(|b| &b.chapter == title)
+    }
+
+    //One bookmark per chapter: sets it at the current row, or removes it when already there.
+    //Returns true when a bookmark was set.
+    pub fn toggle_bookmark(&mut self) -> bool {
+        let bookmark = Bookmark {
+            chapter: self.chapter().title.clone(),
+            scroll: self.scroll,
+        };
+        match self.bookmark_index() {
+            Some(i) if self.bookmarks[i] == bookmark => {
+                self.bookmarks.remove(i);
+                false
+            }
+            Some(i) => {
+                self.bookmarks[i] = bookmark;
+                true
+            }
+            None => {
+                self.bookmarks.push(bookmark);
+                true
+            }
+        }
+    }
+
+    //Jumps to the first bookmark after the current position, in reading order
+    pub fn next_bookmark(&mut self) -> Option<&Bookmark> {
+        let position = |b: &Bookmark| {
+            self.chapters
+                .iter()
+                .position(|c| c.title == b.chapter)
+                .map(|i| (i, b.scroll))
+        };
+        let mut marks: Vec<(usize, u16)> = self.bookmarks.iter().filter_map(position).collect();
+        marks.sort();
+        let current = (self.selected, self.scroll);
+        let (chapter, scroll) = marks
+            .iter()
+            .find(|m| **m > current)
+            .or_else(|| marks.first())
+            .copied()?;
+        self.selected = chapter;
+        self.scroll = scroll.min(self.max_scroll());
+        self.bookmarks
+            .iter()
+            .find(|b| position(b) == Some((chapter, scroll)))
+    }
+}
+
+pub fn render_chapters<'a>(browser: &LoreBrowser) -> List<'a> {
+    let items: Vec<ListItem> = browser
+        .chapters
+        .iter()
+        .map(|chapter| {
+            let marked = browser.bookmarks.iter().any(|b| b.chapter == chapter.title);
+            ListItem::new(Spans::from(vec![
+                Span::styled(
+                    if marked { "★ " } else { "  " },
+                    Style::default().fg(Color::Yellow),
+                ),
+                Span::raw(chapter.title.clone()),
+            ]))
+        })
+        .collect();
+    List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default().fg(Color::White))
+                .title("Kapitel")
+                .border_type(BorderType::Plain),
+        )
+        .highlight_style(
+            Style::default()
+                .bg(Color::Yellow)
+                .fg(Color::Black)
+                .add_modifier(Modifier::BOLD),
+        )
+}
+
+pub fn lore_block<'a>(browser: &LoreBrowser) -> Block<'a> {
+    let total = wrapped_height(&browser.chapter().lines, browser.width);
+    Block::default()
+        .borders(Borders::LEFT | Borders::RIGHT | Borders::TOP)
+        .style(Style::default().fg(Color::Cyan))
+        .title(format!(
+            "{} ({}/{}) ←/→ kapitel, PgUp/PgDn sida, b bokmärke, g nästa bokmärke",
+            browser.chapter().title,
+            (browser.scroll + browser.height).min(total),
+            total
+        ))
+}
+
+pub fn render_chapter<'a>(browser: &LoreBrowser) -> Paragraph<'a> {
+    Paragraph::new(Text::from(browser.chapter().lines.clone()))
+        .style(Style::default().fg(Color::White))
+        .wrap(Wrap { trim: true })
+        .scroll((browser.scroll, 0))
+        .block(lore_block(browser))
+}
+
+#[cfg(test)]
+#[test]
+fn test_lore_chapters() {
+    use tui::{backend::TestBackend, layout::Rect, Terminal};
+
+    let mut chapters = chapters(LORE);
+    let notes = "## Kua\nEn **lång** rad om *Kua* som bryts, med punkter:\n\n- Coriolis\n- Mira\n";
+    chapters.extend(
+        markdown::split_chapters("anteckningar", notes)
+            .into_iter()
+            .map(|(title, body)| Chapter {
+                title,
+                lines: markdown::render(&body),
+            }),
+    );
+    let browser = LoreBrowser::new(chapters, Vec::new());
+    let titles: Vec<&str> = browser.chapters.iter().map(|c| c.title.as_str()).collect();
+    assert_eq!(titles[0], "DEN TREDJE HORISONTEN");
+    assert!(titles.contains(&"ZENIT OCH NADIR"));
+    assert!(titles.contains(&"FRAKTIONERNA"));
+    assert!(titles.contains(&"Kua"));
+    assert!(browser.chapters.iter().all(|c| !c.lines.is_empty()));
+
+    //The computed height must match what tui draws, or the end is cut off
+    for width in [20, 37, 64] {
+        for chapter in browser.chapters.iter() {
+            let height = wrapped_height(&chapter.lines, width);
+            let backend = TestBackend::new(width, height + 2);
+            let mut terminal = Terminal::new(backend).unwrap();
+            terminal
+                .draw(|f| {
+                    let paragraph =
+                        Paragraph::new(Text::from(chapter.lines.clone())).wrap(Wrap { trim: true });
+                    f.render_widget(paragraph, Rect::new(0, 0, width, height + 2));
+                })
+                .unwrap();
+            let buffer = terminal.backend().buffer();
+            let drawn = (0..height + 2)
+                .filter(|y| (0..width).any(|x| buffer.get(x, *y).symbol != " "))
+                .max()
+                .map_or(0, |y| y + 1);
+            assert_eq!(drawn, height, "{} at width {}", chapter.title, width);
+        }
+    }
+
+    let mut browser = browser;
+    browser.set_view(40, 10);
+    browser.scroll_down(u16::MAX);
+    assert_eq!(browser.scroll, browser.max_scroll());
+    assert!(browser.toggle_bookmark());
+    browser.next_chapter();
+    assert_eq!(browser.scroll, 0);
+    let title = browser.next_bookmark().unwrap().chapter.clone();
+    assert_eq!(title, browser.chapters[0].title);
+    assert_eq!(browser.selected, 0);
+    assert!(!browser.toggle_bookmark());
+    assert!(browser.bookmarks.is_empty());
+}
@@ -4,6 +4,7 @@ use crossterm::{
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use serde::{Deserialize, Serialize};
+use std::env;
 use std::fs;
 use std::io;
 use std::sync::mpsc;
@@ -22,22 +23,96 @@ use tui::{
     Frame, Terminal,
 };
 mod banner;
+mod campaign;
+mod cli;
+mod darkness;
+mod dice;
+mod encounter;
+mod faction;
+mod formats;
+mod homebrew;
+mod icon;
+mod import;
+mod journal;
 mod lore;
+mod lorebook;
+mod markdown;
+mod pdf;
+mod power;
+mod query;
+mod rpc;
+mod schema;
+mod screen;
+mod session;
+mod sheet;
+mod ship;
+mod storage;
+mod tables;
+mod travel;
 use banner::BANNER;
-use lore::LORE;
+use campaign::render_campaigns;
+use darkness::{render_darkness, DarknessInput, DarknessPool};
+use dice::{roll_pool, skill_pool};
+use encounter::{
+    render_encounter_popup, render_encounters, root_tables, EncounterGenerator, EncounterTable,
+};
+use faction::{render_factions, Faction, Party};
+use homebrew::source_span;
+use icon::{render_icons, Icon};
+use journal::{render_journal, render_journal_input, EntryKind, Journal};
+use lorebook::{render_chapter, render_chapters, Bookmark, LoreBrowser};
+use power::{render_power_popup, Powers};
+use screen::Screen;
+use session::{render_feed, render_session, Update};
+use sheet::{render_sheet, SheetFormat};
+use ship::{render_ship_combat, render_shipyard, Module, Ship, ShipCombat};
+use travel::{render_map, render_travel, Horizon, TravelPlanner};
 const SKILL_DB: &str = "./data/skills.json";
 const CHARACTER_DB: &str = "./data/character.json";
 const WEAPON_DB: &str = "./data/weapons.json";
 const ITEM_DB: &str = "./data/items.json";
 const ARMOR_DB: &str = "./data/armor.json";
+const SHIP_DB: &str = "./data/ship.json";
+const MODULE_DB: &str = "./data/modules.json";
+const SYSTEM_DB: &str = "./data/systems.json";
+const ENCOUNTER_DB: &str = "./data/encounters.json";
+const JOURNAL_DB: &str = "./data/journal.json";
+const BOOKMARK_DB: &str = "./data/bookmarks.json";
+const FACTION_DB: &str = "./data/factions.json";
+const PARTY_DB: &str = "./data/party.json";
+const ICON_DB: &str = "./data/icons.json";
+const POWER_DB: &str = "./data/powers.json";
+const DARKNESS_DB: &str = "./data/darkness.json";
+const JOURNAL_EXPORT: &str = "./journal.md";
+const SHEET_EXPORT_DIR: &str = "./sheets";
+
+//Every table, in the order migrate-from-json copies them
+const DATA_FILES: [&str; 16] = [
+    SKILL_DB,
+    CHARACTER_DB,
+    WEAPON_DB,
+    ITEM_DB,
+    ARMOR_DB,
+    SHIP_DB,
+    MODULE_DB,
+    SYSTEM_DB,
+    ENCOUNTER_DB,
+    JOURNAL_DB,
+    BOOKMARK_DB,
+    FACTION_DB,
+    PARTY_DB,
+    ICON_DB,
+    POWER_DB,
+    DARKNESS_DB,
+];
 
 #[cfg(test)]
 #[test]
 fn test_path() {
     use std::path::Path;
-    const NUMPATHS: usize = 5;
-    let paths: [&str; NUMPATHS] = [SKILL_DB, CHARACTER_DB, WEAPON_DB, ITEM_DB, ARMOR_DB];
-    paths.map(|p| assert_eq!(Path::new(p).exists(), true));
+    DATA_FILES
+        .iter()
+        .for_each(|p| assert!(Path::new(p).exists()));
 }
 
 #[derive(Error, Debug)]
@@ -46,6 +121,38 @@ pub enum Error {
     ReadDBError(#[from] io::Error),
     #[error("error parsing the DB file: {0}")]
     ParseDBError(#[from] serde_json::Error),
+    #[error("error writing the DB file: {0}")]
+    WriteDBError(io::Error),
+    #[error("error parsing the TOML DB file: {0}")]
+    ParseTomlError(#[from] toml::de::Error),
+    #[error("error parsing the RON DB file: {0}")]
+    ParseRonError(#[from] ron::error::SpannedError),
+    #[error("error serializing the DB file: {0}")]
+    SerializeError(String),
+    #[error("unknown data file format: {0} (json, toml or ron)")]
+    UnknownFormat(String),
+    #[error("error in the SQLite DB: {0}")]
+    SqliteError(#[from] rusqlite::Error),
+    #[error("table {0} is missing from the DB, run migrate-from-json")]
+    MissingTable(String),
+    #[error("{0} has schema version {1}, newer than this program supports")]
+    NewerSchema(String, u32),
+    #[error("invalid campaign name: {0} (letters, digits, - and _)")]
+    InvalidCampaignName(String),
+    #[error("no campaign named {0}, create it with new-campaign")]
+    UnknownCampaign(String),
+    #[error("campaign {0} already exists")]
+    CampaignExists(String),
+    #[error("error creating the PDF: {0}")]
+    PdfError(#[from] printpdf::Error),
+    #[error("network error: {0}")]
+    NetworkError(io::Error),
+    #[error("unexpected message from the host: {0}")]
+    Protocol(String),
+    #[error("{0} exists and is not a socket")]
+    NotASocket(String),
+    #[error("a roll has at most {} dice, not {0}", dice::MAX_POOL)]
+    PoolTooLarge(u8),
 }
 
 enum Event<I> {
@@ -58,9 +165,17 @@ struct Item {
     id: usize,
     name: String,
     description: String,
+    #[serde(default)]
+    cost: u32,
+    //Normal items weigh 1, light 0.5, heavy 2 and tiny 0
+    #[serde(default)]
+    weight: f32,
+    //Homebrew overlay the entry comes from, None for the core rules
+    #[serde(default, skip_serializing)]
+    source: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Default)]
 struct Appearance {
     face: String,
     clothing: String,
@@ -74,6 +189,9 @@ struct Armor {
     addons: String,
     tech: String,
     comment: String,
+    //Homebrew overlay the entry comes from, None for the core rules
+    #[serde(default, skip_serializing)]
+    source: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -87,9 +205,12 @@ struct Weapon {
     räckvidd: String,
     övrigt: String,
     kostnad: u32,
+    //Homebrew overlay the entry comes from, None for the core rules
+    #[serde(default, skip_serializing)]
+    source: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Default)]
 struct Kvalificerade {
     befäl: u8,
     datadjinn: u8,
@@ -101,7 +222,7 @@ struct Kvalificerade {
     vetenskap: u8,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Default)]
 struct Allmanna {
     kraftprov: u8,
     manipulera: u8,
@@ -113,13 +234,13 @@ struct Allmanna {
     överlevnad: u8,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Default)]
 struct Fardigheter {
     allmanna: Allmanna,
     kvalificerade: Kvalificerade,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Default)]
 struct Grundegenskaper {
     styrka: u8,
     kyla: u8,
@@ -127,7 +248,7 @@ struct Grundegenskaper {
     känsla: u8,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Default)]
 struct Character {
     id: usize,
     name: String,
@@ -139,14 +260,34 @@ struct Character {
     background: String,
     upbringing: String,
     group_concept: String,
+    #[serde(default)]
     skill_ids: Vec<usize>,
+    #[serde(default)]
     weapon_ids: Vec<usize>,
+    #[serde(default)]
     armor_ids: Vec<usize>,
+    #[serde(default)]
     gear_ids: Vec<usize>,
     birr: u32,
+    #[serde(default)]
     appearance: Appearance,
     grundegenskaper: Grundegenskaper,
     fardigheter: Fardigheter,
+    #[serde(default)]
+    hit_points: u8,
+    #[serde(default)]
+    radiation: u8,
+    //Notable events, newest last
+    #[serde(default)]
+    history: Vec<String>,
+    #[serde(default)]
+    power_ids: Vec<usize>,
+    //Standing with each faction by id, see faction::standing_label
+    #[serde(default)]
+    reputation: faction::Reputation,
+    //NPCs whose stats the player screen hides
+    #[serde(default)]
+    gm_only: bool,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -155,6 +296,9 @@ struct Skill {
     name: String,
     description: String,
     category: String,
+    //Homebrew overlay the entry comes from, None for the core rules
+    #[serde(default, skip_serializing)]
+    source: Option<String>,
 }
 
 #[derive(PartialEq, Copy, Clone, Debug)]
@@ -164,6 +308,13 @@ enum MenuItem {
     Skills,
     Items,
     Lore,
+    Ship,
+    Shipyard,
+    Travel,
+    Encounters,
+    Factions,
+    Icons,
+    Journal,
 }
 impl From<MenuItem> for usize {
     fn from(input: MenuItem) -> usize {
@@ -173,11 +324,49 @@ impl From<MenuItem> for usize {
             MenuItem::Skills => 2,
             MenuItem::Items => 3,
             MenuItem::Lore => 4,
+            MenuItem::Ship => 5,
+            MenuItem::Shipyard => 6,
+            MenuItem::Travel => 7,
+            MenuItem::Encounters => 8,
+            MenuItem::Factions => 9,
+            MenuItem::Icons => 10,
+            MenuItem::Journal => 11,
         }
     }
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let screen = Screen::from_args(&mut args);
+    let mut active_campaign = match cli::open_campaign(&mut args) {
+        Ok(campaign) => campaign,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    //A player's terminal UI shows the host's data instead of its own
+    let session = match args.first().map(String::as_str) {
+        Some("connect") => match cli::connect(&args[1..]) {
+            Ok(client) => {
+                args.clear();
+                Some(client)
+            }
+            Err(e) => {
+                eprintln!("error: {}", e);
+                std::process::exit(1);
+            }
+        },
+        _ => None,
+    };
+    if !args.is_empty() {
+        if let Err(e) = cli::run(&args, screen) {
+            eprintln!("error: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     enable_raw_mode().expect("can run in raw mode");
 
     let (tx, rx) = mpsc::channel();
@@ -195,15 +384,19 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }
             }
 
-            if last_tick.elapsed() >= tick_rate {
-                if let Ok(_) = tx.send(Event::Tick) {
-                    last_tick = Instant::now();
-                }
+            if last_tick.elapsed() >= tick_rate && tx.send(Event::Tick).is_ok() {
+                last_tick = Instant::now();
             }
         }
     });
 
     let mut show_skill_popup = false;
+    let mut show_power_popup = false;
+    let mut list_state_powers = ListState::default();
+    let mut power_result = String::new();
+    let mut show_roll_popup = false;
+    let mut list_state_rolls = ListState::default();
+    let mut roll_result = String::new();
     let mut select_skill_list = false;
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen)?;
@@ -211,12 +404,19 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let mut terminal = Terminal::new(backend)?;
     terminal.clear()?;
 
-    let menu_titles = vec![
+    let menu_titles = [
         "Hem",
         "Karaktärer",
         "Talanger",
         "Utrustning",
         "Lore",
+        "Skepp",
+        "Varv",
+        "Resa",
+        "Möten",
+        "Fraktioner",
+        "Ikoner",
+        "Journal",
         "Avsluta",
     ];
     let mut active_menu_item = MenuItem::Home;
@@ -227,9 +427,72 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let mut charcounter = 0;
     let mut itemcounter = 0;
     let mut homecounter = 0;
-    let mut scroll = 1;
+    //A database migrated before bookmarks existed has no table for them yet
+    let mut lore = LoreBrowser::new(
+        lorebook::load(active_campaign.as_deref()).expect("can read lore"),
+        read_bookmark_db().unwrap_or_default(),
+    );
+    let mut list_state_lore = ListState::default();
     let mut current_menu: MenuItem = MenuItem::Home;
     list_state_skills.select(Some(0));
+    let mut list_state_ship = ListState::default();
+    list_state_ship.select(Some(0));
+    let mut ship = read_ship_db().expect("can fetch ship");
+    let mut ship_combat = ShipCombat::new(&ship, &read_module_db().expect("can fetch list"));
+    let mut list_state_modules = ListState::default();
+    list_state_modules.select(Some(0));
+    let mut shipyard_message = String::new();
+    let horizon = read_system_db().expect("can fetch system list");
+    let mut travel_planner = TravelPlanner::new(horizon.systems[0].id);
+    let mut list_state_systems = ListState::default();
+    list_state_systems.select(Some(0));
+    let encounter_tables = read_encounter_db().expect("can fetch encounter tables");
+    let mut encounter_generator = EncounterGenerator::new(rand::random());
+    let mut list_state_encounters = ListState::default();
+    list_state_encounters.select(Some(0));
+    let mut journal = read_journal_db().expect("can fetch journal");
+    let mut journal_input: Option<String> = None;
+    //Whether the note being typed is only for the GM
+    let mut journal_secret = false;
+    let mut journal_message = String::new();
+    let mut journal_scroll: u16 = 0;
+    let factions = read_faction_db().expect("can fetch factions");
+    let mut list_state_factions = ListState::default();
+    list_state_factions.select(Some(0));
+    let mut party = read_party_db().expect("can fetch party");
+    //0 is the party, n is the nth character
+    let mut faction_target = 0;
+    let icons = read_icon_db().expect("can fetch icons");
+    let powers = read_power_db().expect("can fetch powers");
+    let mut darkness = read_darkness_db().expect("can fetch darkness points");
+    let mut darkness_input: Option<DarknessInput> = None;
+    //The GM can hide the pool when the players can see the screen, it is never shown
+    //in player mode
+    let mut show_darkness = screen == Screen::Gm;
+    let mut list_state_icons = ListState::default();
+    list_state_icons.select(Some(0));
+    //Homebrew conflicts and unknown icons are easy to miss, so they are shown until the
+    //first status message
+    let mut status = match homebrew::report() {
+        Ok(report) => report
+            .into_iter()
+            .filter(|line| !line.starts_with("overlay"))
+            .chain(icon::validate(
+                &read_character_db().expect("can fetch list"),
+                &icons,
+            ))
+            .collect::<Vec<_>>()
+            .join(" | "),
+        Err(e) => e.to_string(),
+    };
+    let campaigns = campaign::list();
+    let mut list_state_campaigns = ListState::default();
+    list_state_campaigns.select(Some(
+        active_campaign
+            .as_ref()
+            .and_then(|a| campaigns.iter().position(|c| c == a))
+            .map_or(0, |i| i + 1),
+    ));
 
     loop {
         terminal.draw(|rect| {
@@ -246,7 +509,15 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     .as_ref(),
                 )
                 .split(size);
-            let copyright = Paragraph::new("Coriolis Beyond 2022 - No rights reserved")
+            let footer = if status.is_empty() {
+                format!(
+                    "Coriolis Beyond 2022 - No rights reserved - {}",
+                    screen.label()
+                )
+            } else {
+                status.clone()
+            };
+            let copyright = Paragraph::new(footer)
                 .style(Style::default().fg(Color::DarkGray))
                 .alignment(Alignment::Center)
                 .block(
@@ -286,19 +557,47 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             let refresh_needed: bool = current_menu == active_menu_item;
             match active_menu_item {
                 MenuItem::Home => {
-                    homecounter = homecounter + 1;
+                    homecounter += 1;
                     let home_chunks = Layout::default()
                         .direction(Direction::Vertical)
                         .constraints([Constraint::Min(30), Constraint::Ratio(3, 1)].as_ref())
                         .split(chunks[1]);
+                    let lower_chunks = Layout::default()
+                        .direction(Direction::Horizontal)
+                        .constraints(
+                            [Constraint::Percentage(70), Constraint::Percentage(30)].as_ref(),
+                        )
+                        .split(home_chunks[1]);
                     let (banner_text, home_text) = render_home();
+                    let side_chunks = Layout::default()
+                        .direction(Direction::Vertical)
+                        .constraints(
+                            [Constraint::Percentage(40), Constraint::Percentage(60)].as_ref(),
+                        )
+                        .split(lower_chunks[1]);
                     rect.render_widget(banner_text, home_chunks[0]);
-                    rect.render_widget(home_text, home_chunks[1]);
+                    rect.render_widget(home_text, lower_chunks[0]);
+                    match &session {
+                        Some(client) => rect.render_widget(render_session(client), side_chunks[0]),
+                        None => rect.render_stateful_widget(
+                            render_campaigns(&campaigns, active_campaign.as_deref()),
+                            side_chunks[0],
+                            &mut list_state_campaigns,
+                        ),
+                    }
+                    //Players never see the Darkness Points, so the roll feed takes their place
+                    match &session {
+                        Some(client) => rect.render_widget(render_feed(client), side_chunks[1]),
+                        None => rect.render_widget(
+                            render_darkness(&darkness, show_darkness, darkness_input.as_ref()),
+                            side_chunks[1],
+                        ),
+                    }
                 }
                 MenuItem::Character => {
                     if refresh_needed {
                         //derbug counter
-                        charcounter = charcounter + 1;
+                        charcounter += 1;
                         //Big chunk, displays enitre character screen
                         let character_chunks = Layout::default()
                             .direction(Direction::Horizontal)
@@ -349,7 +648,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                             weapon_ids,
                             gear_ids,
                             armor_ids,
-                        ) = render_character(&mut list_state);
+                        ) = render_character(&mut list_state, screen);
                         let (left1, _right2) =
                             render_char_skills(&mut list_state_skills, &char_skills_ids); // char_skills
                         let weapons = render_character_weapons(weapon_ids);
@@ -373,14 +672,41 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                         rect.render_widget(armor, inside_chunks[2]);
                         rect.render_widget(weapons, inside_chunks[3]);
                         if show_skill_popup {
-                            render_popup(rect, &mut list_state_skills, char_skills_ids)
+                            render_popup(rect, &list_state_skills, char_skills_ids)
+                        }
+                        if show_roll_popup {
+                            let characters = read_character_db().expect("can fetch list");
+                            if let Some(character) =
+                                list_state.selected().and_then(|i| characters.get(i))
+                            {
+                                render_roll_popup(
+                                    rect,
+                                    character,
+                                    &mut list_state_rolls,
+                                    &roll_result,
+                                );
+                            }
+                        }
+                        if show_power_popup {
+                            let characters = read_character_db().expect("can fetch list");
+                            if let Some(character) =
+                                list_state.selected().and_then(|i| characters.get(i))
+                            {
+                                render_power_popup(
+                                    rect,
+                                    character,
+                                    &powers.known(character),
+                                    &mut list_state_powers,
+                                    &power_result,
+                                );
+                            }
                         }
                     }
                 }
                 //debug
                 MenuItem::Skills => {
                     //derbug counter
-                    skillcounter = skillcounter + 1;
+                    skillcounter += 1;
                     let skill_chunks = Layout::default()
                         .direction(Direction::Horizontal)
                         .constraints(
@@ -394,7 +720,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }
                 MenuItem::Items => {
                     //derbug counter
-                    itemcounter = itemcounter + 1;
+                    itemcounter += 1;
                     let item_chunks = Layout::default()
                         .direction(Direction::Horizontal)
                         .constraints(
@@ -407,24 +733,167 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }
                 MenuItem::Lore => {
                     let lore_chunks = Layout::default()
+                        .direction(Direction::Horizontal)
+                        .constraints(
+                            [Constraint::Percentage(25), Constraint::Percentage(75)].as_ref(),
+                        )
+                        .split(chunks[1]);
+                    let text_area = lorebook::lore_block(&lore).inner(lore_chunks[1]);
+                    lore.set_view(text_area.width, text_area.height);
+                    list_state_lore.select(Some(lore.selected));
+                    rect.render_stateful_widget(
+                        render_chapters(&lore),
+                        lore_chunks[0],
+                        &mut list_state_lore,
+                    );
+                    rect.render_widget(render_chapter(&lore), lore_chunks[1]);
+                }
+                MenuItem::Ship => {
+                    let ship_chunks = Layout::default()
+                        .direction(Direction::Horizontal)
+                        .constraints(
+                            [
+                                Constraint::Percentage(25),
+                                Constraint::Percentage(35),
+                                Constraint::Percentage(40),
+                            ]
+                            .as_ref(),
+                        )
+                        .split(chunks[1]);
+                    let status_chunks = Layout::default()
                         .direction(Direction::Vertical)
-                        .constraints([Constraint::Max(1), Constraint::Percentage(95)].as_ref())
-                        .horizontal_margin(10)
-                        .vertical_margin(1)
+                        .constraints([Constraint::Length(9), Constraint::Min(3)].as_ref())
+                        .split(ship_chunks[2]);
+                    let characters = read_character_db().expect("can fetch character list");
+                    let (phases, actions, status, log) =
+                        render_ship_combat(&ship_combat, &characters);
+                    rect.render_widget(phases, ship_chunks[0]);
+                    rect.render_stateful_widget(actions, ship_chunks[1], &mut list_state_ship);
+                    rect.render_widget(status, status_chunks[0]);
+                    rect.render_widget(log, status_chunks[1]);
+                }
+                MenuItem::Shipyard => {
+                    let shipyard_chunks = Layout::default()
+                        .direction(Direction::Horizontal)
+                        .constraints(
+                            [
+                                Constraint::Percentage(30),
+                                Constraint::Percentage(35),
+                                Constraint::Percentage(35),
+                            ]
+                            .as_ref(),
+                        )
                         .split(chunks[1]);
-                    let lore_block = Block::default()
-                        .borders(Borders::LEFT | Borders::RIGHT)
-                        .style(Style::default().fg(Color::Cyan));
-
-                    let lore_text = Paragraph::new(LORE)
-                        .style(
-                            Style::default()
-                                .add_modifier(Modifier::BOLD)
-                                .fg(Color::White),
+                    let catalogue = read_module_db().expect("can fetch module list");
+                    let party_birr = read_character_db()
+                        .expect("can fetch character list")
+                        .iter()
+                        .map(|c| c.birr)
+                        .sum();
+                    let (modules, module_detail, summary) = render_shipyard(
+                        &ship,
+                        &catalogue,
+                        list_state_modules.selected(),
+                        party_birr,
+                        &shipyard_message,
+                    );
+                    rect.render_stateful_widget(
+                        modules,
+                        shipyard_chunks[0],
+                        &mut list_state_modules,
+                    );
+                    rect.render_widget(module_detail, shipyard_chunks[1]);
+                    rect.render_widget(summary, shipyard_chunks[2]);
+                }
+                MenuItem::Travel => {
+                    let travel_chunks = Layout::default()
+                        .direction(Direction::Horizontal)
+                        .constraints(
+                            [
+                                Constraint::Length(18),
+                                Constraint::Length(travel::MAP_WIDTH as u16 + 2),
+                                Constraint::Min(20),
+                            ]
+                            .as_ref(),
+                        )
+                        .split(chunks[1]);
+                    let characters = read_character_db().expect("can fetch character list");
+                    let skills = read_skill_db().expect("can fetch skill list");
+                    let (systems, route_detail) =
+                        render_travel(&horizon, &travel_planner, &characters, &skills);
+                    let map = render_map(&horizon, &travel_planner);
+                    rect.render_stateful_widget(systems, travel_chunks[0], &mut list_state_systems);
+                    rect.render_widget(map, travel_chunks[1]);
+                    rect.render_widget(route_detail, travel_chunks[2]);
+                }
+                MenuItem::Encounters => {
+                    let encounter_chunks = Layout::default()
+                        .direction(Direction::Horizontal)
+                        .constraints(
+                            [Constraint::Percentage(20), Constraint::Percentage(80)].as_ref(),
+                        )
+                        .split(chunks[1]);
+                    let (tables, detail) =
+                        render_encounters(&encounter_tables, &encounter_generator, screen);
+                    rect.render_stateful_widget(
+                        tables,
+                        encounter_chunks[0],
+                        &mut list_state_encounters,
+                    );
+                    rect.render_widget(detail, encounter_chunks[1]);
+                    if encounter_generator.show_popup {
+                        render_encounter_popup(rect, &encounter_generator.results);
+                    }
+                }
+                MenuItem::Factions => {
+                    let faction_chunks = Layout::default()
+                        .direction(Direction::Horizontal)
+                        .constraints(
+                            [Constraint::Percentage(25), Constraint::Percentage(75)].as_ref(),
                         )
-                        .scroll((scroll, 1))
-                        .block(lore_block);
-                    rect.render_widget(lore_text, lore_chunks[1]);
+                        .split(chunks[1]);
+                    let characters = read_character_db().expect("can fetch list");
+                    let (list, detail) = render_factions(
+                        &factions,
+                        list_state_factions.selected(),
+                        &party,
+                        &characters,
+                        faction_target,
+                        &journal,
+                        screen,
+                    );
+                    rect.render_stateful_widget(list, faction_chunks[0], &mut list_state_factions);
+                    rect.render_widget(detail, faction_chunks[1]);
+                }
+                MenuItem::Icons => {
+                    let icon_chunks = Layout::default()
+                        .direction(Direction::Horizontal)
+                        .constraints(
+                            [Constraint::Percentage(25), Constraint::Percentage(75)].as_ref(),
+                        )
+                        .split(chunks[1]);
+                    let (list, detail) = render_icons(
+                        &icons,
+                        list_state_icons.selected(),
+                        &read_skill_db().expect("can fetch list"),
+                        &read_character_db().expect("can fetch list"),
+                    );
+                    rect.render_stateful_widget(list, icon_chunks[0], &mut list_state_icons);
+                    rect.render_widget(detail, icon_chunks[1]);
+                }
+                MenuItem::Journal => {
+                    let journal_chunks = Layout::default()
+                        .direction(Direction::Vertical)
+                        .constraints([Constraint::Min(3), Constraint::Length(3)].as_ref())
+                        .split(chunks[1]);
+                    let entries = render_journal(&journal, journal_scroll, screen);
+                    let input = render_journal_input(
+                        journal_input.as_ref(),
+                        journal_secret,
+                        &journal_message,
+                    );
+                    rect.render_widget(entries, journal_chunks[0]);
+                    rect.render_widget(input, journal_chunks[1]);
                 }
             }
             rect.render_widget(copyright, chunks[2]);
@@ -432,6 +901,68 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
         current_menu = active_menu_item;
         match rx.recv()? {
+            Event::Input(event) if journal_input.is_some() => match event.code {
+                KeyCode::Enter => {
+                    let text = journal_input.take().unwrap_or_default();
+                    if !text.trim().is_empty() {
+                        if journal_secret {
+                            journal.record_secret(EntryKind::Note, text);
+                        } else {
+                            journal.record(EntryKind::Note, text);
+                        }
+                        write_db(JOURNAL_DB, &journal)?;
+                    }
+                }
+                KeyCode::Esc => journal_input = None,
+                KeyCode::Backspace => {
+                    if let Some(buffer) = journal_input.as_mut() {
+                        buffer.pop();
+                    }
+                }
+                KeyCode::Char(c) => {
+                    if let Some(buffer) = journal_input.as_mut() {
+                        buffer.push(c);
+                    }
+                }
+                _ => {}
+            },
+            Event::Input(event) if darkness_input.is_some() => match event.code {
+                KeyCode::Enter => {
+                    if let Some(input) = darkness_input.take() {
+                        let reason = match input.reason.trim() {
+                            "" => String::from("Justerat av SL"),
+                            reason => reason.to_string(),
+                        };
+                        let delta = darkness.change(input.delta, reason.clone());
+                        journal
+                            .record_secret(EntryKind::Darkness, format!("{:+} {}", delta, reason));
+                        write_db(DARKNESS_DB, &darkness)?;
+                        write_db(JOURNAL_DB, &journal)?;
+                    }
+                }
+                KeyCode::Esc => darkness_input = None,
+                KeyCode::Up => {
+                    if let Some(input) = darkness_input.as_mut() {
+                        input.delta += 1;
+                    }
+                }
+                KeyCode::Down => {
+                    if let Some(input) = darkness_input.as_mut() {
+                        input.delta -= 1;
+                    }
+                }
+                KeyCode::Backspace => {
+                    if let Some(input) = darkness_input.as_mut() {
+                        input.reason.pop();
+                    }
+                }
+                KeyCode::Char(c) => {
+                    if let Some(input) = darkness_input.as_mut() {
+                        input.reason.push(c);
+                    }
+                }
+                _ => {}
+            },
             Event::Input(event) => match event.code {
                 KeyCode::Char('a') => {
                     terminal.clear()?;
@@ -446,7 +977,296 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 KeyCode::Char('t') => active_menu_item = MenuItem::Skills,
                 KeyCode::Char('u') => active_menu_item = MenuItem::Items,
                 KeyCode::Char('l') => active_menu_item = MenuItem::Lore,
+                KeyCode::Char('s') => active_menu_item = MenuItem::Ship,
+                KeyCode::Char('v') => active_menu_item = MenuItem::Shipyard,
+                KeyCode::Char('r') => active_menu_item = MenuItem::Travel,
+                KeyCode::Char('m') => active_menu_item = MenuItem::Encounters,
+                KeyCode::Char('j') => active_menu_item = MenuItem::Journal,
+                KeyCode::Char('f') => active_menu_item = MenuItem::Factions,
+                KeyCode::Char(c @ ('+' | '-'))
+                    if active_menu_item == MenuItem::Home && show_darkness =>
+                {
+                    darkness_input = Some(DarknessInput {
+                        delta: if c == '+' { 1 } else { -1 },
+                        reason: String::new(),
+                    });
+                }
+                KeyCode::Char('d')
+                    if active_menu_item == MenuItem::Home && screen == Screen::Gm =>
+                {
+                    show_darkness = !show_darkness;
+                }
+                KeyCode::Char('i') => active_menu_item = MenuItem::Icons,
+                KeyCode::Tab if active_menu_item == MenuItem::Factions => {
+                    let amount_characters = read_character_db().expect("can fetch list").len();
+                    faction_target = (faction_target + 1) % (amount_characters + 1);
+                }
+                KeyCode::Char(c @ ('+' | '-')) if active_menu_item == MenuItem::Factions => {
+                    if let Some(faction) = list_state_factions.selected().map(|i| &factions[i]) {
+                        let delta = if c == '+' { 1 } else { -1 };
+                        let mut characters = read_character_db().expect("can fetch list");
+                        let who = faction::target_name(&characters, faction_target);
+                        let standing = match faction_target.checked_sub(1) {
+                            Some(i) => {
+                                let standing = faction::adjust(
+                                    &mut characters[i].reputation,
+                                    faction.id,
+                                    delta,
+                                );
+                                write_db(CHARACTER_DB, &characters)?;
+                                standing
+                            }
+                            None => {
+                                let standing =
+                                    faction::adjust(&mut party.reputation, faction.id, delta);
+                                write_db(PARTY_DB, &party)?;
+                                standing
+                            }
+                        };
+                        faction::record_change(&mut journal, &who, faction, standing);
+                        write_db(JOURNAL_DB, &journal)?;
+                    }
+                }
+                KeyCode::Char('n') if active_menu_item == MenuItem::Journal => {
+                    journal_input = Some(String::new());
+                    journal_secret = false;
+                }
+                KeyCode::Char('g')
+                    if active_menu_item == MenuItem::Journal && screen == Screen::Gm =>
+                {
+                    journal_input = Some(String::new());
+                    journal_secret = true;
+                }
+                KeyCode::Char('x') if active_menu_item == MenuItem::Journal => {
+                    journal_message = match fs::write(JOURNAL_EXPORT, journal.to_markdown(screen)) {
+                        Ok(()) => format!("Journalen exporterad till {}", JOURNAL_EXPORT),
+                        Err(e) => e.to_string(),
+                    };
+                }
+                KeyCode::Tab if active_menu_item == MenuItem::Journal => {
+                    journal.new_session();
+                    write_db(JOURNAL_DB, &journal)?;
+                }
+                KeyCode::Char('e')
+                    if active_menu_item == MenuItem::Character
+                        && !hidden_character(&list_state, screen) =>
+                {
+                    if let Some(selected) = list_state.selected() {
+                        let character =
+                            read_character_db().expect("can fetch list")[selected].clone();
+                        status = match export_sheets(&character) {
+                            Ok(()) => {
+                                format!("{} exporterad till {}", character.name, SHEET_EXPORT_DIR)
+                            }
+                            Err(e) => e.to_string(),
+                        };
+                    }
+                }
+                KeyCode::Char('+') if active_menu_item == MenuItem::Character => {
+                    if let Some(selected) = list_state.selected() {
+                        let mut characters = read_character_db().expect("can fetch list");
+                        let character = &mut characters[selected];
+                        character.experience = character.experience.saturating_add(1);
+                        journal.record(
+                            EntryKind::Xp,
+                            format!("{} +1 XP ({})", character.name, character.experience),
+                        );
+                        write_db(CHARACTER_DB, &characters)?;
+                        write_db(JOURNAL_DB, &journal)?;
+                    }
+                }
+                KeyCode::Char('p')
+                    if active_menu_item == MenuItem::Encounters
+                        && encounter_generator.show_popup =>
+                {
+                    if let Some(pinned) = encounter_generator.pin() {
+                        journal.record_secret(EntryKind::Encounter, pinned);
+                        write_db(JOURNAL_DB, &journal)?;
+                    }
+                }
+                KeyCode::Esc if active_menu_item == MenuItem::Encounters => {
+                    encounter_generator.show_popup = false;
+                }
+                KeyCode::Tab if active_menu_item == MenuItem::Encounters => {
+                    encounter_generator.reseed(rand::random());
+                }
+                KeyCode::Backspace if active_menu_item == MenuItem::Encounters => {
+                    encounter_generator.reseed(encounter_generator.seed);
+                }
+                KeyCode::Tab if active_menu_item == MenuItem::Travel => {
+                    travel_planner.toggle_metric();
+                    travel_planner.plan(&mut rand::thread_rng(), &horizon);
+                }
+                KeyCode::Char(' ') if active_menu_item == MenuItem::Travel => {
+                    if let Some(selected) = list_state_systems.selected() {
+                        travel_planner.from = horizon.systems[selected].id;
+                        travel_planner.plan(&mut rand::thread_rng(), &horizon);
+                    }
+                }
+                KeyCode::Tab if active_menu_item == MenuItem::Ship => {
+                    ship_combat.next_phase();
+                    list_state_ship.select(Some(0));
+                }
+                KeyCode::Char('+') if active_menu_item == MenuItem::Ship => {
+                    ship_combat.allocate();
+                }
+                KeyCode::Char('-') if active_menu_item == MenuItem::Ship => {
+                    ship_combat.deallocate();
+                }
+                KeyCode::Delete if active_menu_item == MenuItem::Ship => {
+                    ship_combat.take_damage(1);
+                }
+                KeyCode::Char('o')
+                    if active_menu_item == MenuItem::Character
+                        && !hidden_character(&list_state, screen) =>
+                {
+                    show_power_popup = !show_power_popup;
+                    list_state_powers.select(Some(0));
+                    power_result.clear();
+                }
+                KeyCode::Char('w')
+                    if active_menu_item == MenuItem::Character
+                        && !hidden_character(&list_state, screen) =>
+                {
+                    show_roll_popup = !show_roll_popup;
+                    show_power_popup = false;
+                    list_state_rolls.select(Some(0));
+                    roll_result.clear();
+                }
+                KeyCode::Esc if active_menu_item == MenuItem::Character => {
+                    show_power_popup = false;
+                    show_roll_popup = false;
+                }
+                KeyCode::Down | KeyCode::Up
+                    if active_menu_item == MenuItem::Character && show_roll_popup =>
+                {
+                    let amount = roll_options(&Character::default()).len();
+                    if let Some(selected) = list_state_rolls.selected() {
+                        let next = if event.code == KeyCode::Down {
+                            (selected + 1) % amount
+                        } else {
+                            selected.checked_sub(1).unwrap_or(amount - 1)
+                        };
+                        list_state_rolls.select(Some(next));
+                    }
+                }
+                KeyCode::Enter if active_menu_item == MenuItem::Character && show_roll_popup => {
+                    let characters = read_character_db().expect("can fetch list");
+                    let character = list_state.selected().and_then(|i| characters.get(i));
+                    if let Some(character) = character {
+                        let options = roll_options(character);
+                        if let Some((skill, _, dice)) =
+                            list_state_rolls.selected().and_then(|i| options.get(i))
+                        {
+                            //Connected, the host rolls so everyone sees the same dice
+                            roll_result = match &session {
+                                Some(client) => {
+                                    match client.roll_for(&character.name, skill, *dice) {
+                                        Ok(()) => String::from("Skickat till värden..."),
+                                        Err(e) => e.to_string(),
+                                    }
+                                }
+                                None => {
+                                    let roll = roll_pool(&mut rand::thread_rng(), *dice);
+                                    let text = format!(
+                                        "{}: {} {:?} => {} framgångar",
+                                        character.name,
+                                        skill,
+                                        roll.dice,
+                                        roll.successes()
+                                    );
+                                    journal.record(EntryKind::Roll, text.clone());
+                                    write_db(JOURNAL_DB, &journal)?;
+                                    text
+                                }
+                            };
+                        }
+                    }
+                }
+                KeyCode::Down | KeyCode::Up
+                    if active_menu_item == MenuItem::Character && show_power_popup =>
+                {
+                    let characters = read_character_db().expect("can fetch list");
+                    let known = list_state
+                        .selected()
+                        .and_then(|i| characters.get(i))
+                        .map_or(0, |c| powers.known(c).len());
+                    if let Some(selected) = list_state_powers.selected().filter(|_| known > 0) {
+                        let next = if event.code == KeyCode::Down {
+                            (selected + 1) % known
+                        } else {
+                            selected.checked_sub(1).unwrap_or(known - 1)
+                        };
+                        list_state_powers.select(Some(next));
+                    }
+                }
+                KeyCode::Enter if active_menu_item == MenuItem::Character && show_power_popup => {
+                    let characters = read_character_db().expect("can fetch list");
+                    let character = list_state.selected().and_then(|i| characters.get(i));
+                    if let Some(character) = character {
+                        let known = powers.known(character);
+                        if let Some(power) = list_state_powers.selected().and_then(|i| known.get(i))
+                        {
+                            //Connected, the host rolls and the GM keeps the Darkness Points
+                            if let Some(client) = &session {
+                                power_result = match client.roll_for(
+                                    &character.name,
+                                    &power.name,
+                                    power::activation_pool(character),
+                                ) {
+                                    Ok(()) => String::from("Skickat till värden..."),
+                                    Err(e) => e.to_string(),
+                                };
+                            } else {
+                                let activation = power::activate(
+                                    &mut rand::thread_rng(),
+                                    character,
+                                    power,
+                                    &powers.mishaps,
+                                );
+                                power_result = activation.summary(character, power, screen);
+                                //The journal and the players' roll feed never get the Darkness Points
+                                journal.record(
+                                    EntryKind::Roll,
+                                    activation.summary(character, power, Screen::Player),
+                                );
+                                //Powers that give no points leave the pool alone
+                                if activation.darkness > 0 {
+                                    let reason =
+                                        format!("{} aktiverade {}", character.name, power.name);
+                                    darkness.change(activation.darkness as i32, reason.clone());
+                                    journal.record_secret(
+                                        EntryKind::Darkness,
+                                        format!("+{} {}", activation.darkness, reason),
+                                    );
+                                    write_db(DARKNESS_DB, &darkness)?;
+                                }
+                                write_db(JOURNAL_DB, &journal)?;
+                            }
+                        }
+                    }
+                }
                 KeyCode::Down => {
+                    if active_menu_item == MenuItem::Icons {
+                        if let Some(selected) = list_state_icons.selected() {
+                            list_state_icons.select(Some((selected + 1) % icons.len()));
+                        }
+                    }
+                    if active_menu_item == MenuItem::Factions {
+                        if let Some(selected) = list_state_factions.selected() {
+                            list_state_factions.select(Some((selected + 1) % factions.len()));
+                        }
+                    }
+                    if active_menu_item == MenuItem::Home {
+                        if let Some(selected) = list_state_campaigns.selected() {
+                            //The shared data is listed before the campaigns
+                            if selected >= campaigns.len() {
+                                list_state_campaigns.select(Some(0));
+                            } else {
+                                list_state_campaigns.select(Some(selected + 1));
+                            }
+                        }
+                    }
                     if active_menu_item == MenuItem::Skills {
                         if let Some(selected) = list_state.selected() {
                             let amount_skills = read_skill_db().expect("can fetch list").len();
@@ -491,14 +1311,74 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                     }
-                    if active_menu_item == MenuItem::Lore {
-                        scroll = scroll + 1;
-                        if scroll >= 15 {
-                            scroll = 15;
+                    if active_menu_item == MenuItem::Encounters {
+                        if let Some(selected) = list_state_encounters.selected() {
+                            let amount_tables = root_tables(&encounter_tables).len();
+                            if selected >= amount_tables - 1 {
+                                list_state_encounters.select(Some(0));
+                            } else {
+                                list_state_encounters.select(Some(selected + 1));
+                            }
+                        }
+                    }
+                    if active_menu_item == MenuItem::Travel {
+                        if let Some(selected) = list_state_systems.selected() {
+                            if selected >= horizon.systems.len() - 1 {
+                                list_state_systems.select(Some(0));
+                            } else {
+                                list_state_systems.select(Some(selected + 1));
+                            }
                         }
                     }
+                    if active_menu_item == MenuItem::Shipyard {
+                        if let Some(selected) = list_state_modules.selected() {
+                            let amount_modules = read_module_db().expect("can fetch list").len();
+                            if selected >= amount_modules - 1 {
+                                list_state_modules.select(Some(0));
+                            } else {
+                                list_state_modules.select(Some(selected + 1));
+                            }
+                        }
+                    }
+                    if active_menu_item == MenuItem::Ship {
+                        if let Some(selected) = list_state_ship.selected() {
+                            let amount_actions = ship_combat.phase.actions().len();
+                            if selected >= amount_actions - 1 {
+                                list_state_ship.select(Some(0));
+                            } else {
+                                list_state_ship.select(Some(selected + 1));
+                            }
+                        }
+                    }
+                    if active_menu_item == MenuItem::Journal {
+                        journal_scroll = journal_scroll.saturating_add(1);
+                    }
+                    if active_menu_item == MenuItem::Lore {
+                        lore.scroll_down(1);
+                    }
                 }
                 KeyCode::Up => {
+                    if active_menu_item == MenuItem::Icons {
+                        if let Some(selected) = list_state_icons.selected() {
+                            let previous = selected.checked_sub(1).unwrap_or(icons.len() - 1);
+                            list_state_icons.select(Some(previous));
+                        }
+                    }
+                    if active_menu_item == MenuItem::Factions {
+                        if let Some(selected) = list_state_factions.selected() {
+                            let previous = selected.checked_sub(1).unwrap_or(factions.len() - 1);
+                            list_state_factions.select(Some(previous));
+                        }
+                    }
+                    if active_menu_item == MenuItem::Home {
+                        if let Some(selected) = list_state_campaigns.selected() {
+                            if selected > 0 {
+                                list_state_campaigns.select(Some(selected - 1));
+                            } else {
+                                list_state_campaigns.select(Some(campaigns.len()));
+                            }
+                        }
+                    }
                     if active_menu_item == MenuItem::Items {
                         if let Some(selected) = list_state.selected() {
                             let amount_items = read_item_db().expect("can fetch list").len();
@@ -542,27 +1422,167 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                             }
                         }
                     }
-                    if active_menu_item == MenuItem::Lore {
-                        scroll = scroll - 1;
-                        if scroll <= 0 {
-                            scroll = 1;
+                    if active_menu_item == MenuItem::Encounters {
+                        if let Some(selected) = list_state_encounters.selected() {
+                            let amount_tables = root_tables(&encounter_tables).len();
+                            if selected > 0 {
+                                list_state_encounters.select(Some(selected - 1));
+                            } else {
+                                list_state_encounters.select(Some(amount_tables - 1));
+                            }
                         }
                     }
-                }
-                KeyCode::Right => {
-                    if active_menu_item == MenuItem::Character {
-                        select_skill_list = true;
+                    if active_menu_item == MenuItem::Travel {
+                        if let Some(selected) = list_state_systems.selected() {
+                            if selected > 0 {
+                                list_state_systems.select(Some(selected - 1));
+                            } else {
+                                list_state_systems.select(Some(horizon.systems.len() - 1));
+                            }
+                        }
                     }
-                }
-                KeyCode::Left => {
-                    if active_menu_item == MenuItem::Character {
-                        select_skill_list = false;
+                    if active_menu_item == MenuItem::Shipyard {
+                        if let Some(selected) = list_state_modules.selected() {
+                            let amount_modules = read_module_db().expect("can fetch list").len();
+                            if selected > 0 {
+                                list_state_modules.select(Some(selected - 1));
+                            } else {
+                                list_state_modules.select(Some(amount_modules - 1));
+                            }
+                        }
+                    }
+                    if active_menu_item == MenuItem::Ship {
+                        if let Some(selected) = list_state_ship.selected() {
+                            let amount_actions = ship_combat.phase.actions().len();
+                            if selected > 0 {
+                                list_state_ship.select(Some(selected - 1));
+                            } else {
+                                list_state_ship.select(Some(amount_actions - 1));
+                            }
+                        }
+                    }
+                    if active_menu_item == MenuItem::Journal {
+                        journal_scroll = journal_scroll.saturating_sub(1);
                     }
+                    if active_menu_item == MenuItem::Lore {
+                        lore.scroll_up(1);
+                    }
+                }
+                KeyCode::Right if active_menu_item == MenuItem::Character => {
+                    select_skill_list = true;
+                }
+                KeyCode::Left if active_menu_item == MenuItem::Character => {
+                    select_skill_list = false;
+                }
+                KeyCode::Right if active_menu_item == MenuItem::Lore => lore.next_chapter(),
+                KeyCode::Left if active_menu_item == MenuItem::Lore => lore.previous_chapter(),
+                KeyCode::PageDown if active_menu_item == MenuItem::Lore => {
+                    lore.scroll_down(lore.page());
+                }
+                KeyCode::PageUp if active_menu_item == MenuItem::Lore => {
+                    lore.scroll_up(lore.page());
+                }
+                KeyCode::Char('b') if active_menu_item == MenuItem::Lore => {
+                    let set = lore.toggle_bookmark();
+                    write_db(BOOKMARK_DB, &lore.bookmarks)?;
+                    status = if set {
+                        format!("Bokmärke satt i {}", lore.chapter().title)
+                    } else {
+                        format!("Bokmärke borttaget i {}", lore.chapter().title)
+                    };
+                }
+                KeyCode::Char('g') if active_menu_item == MenuItem::Lore => {
+                    status = match lore.next_bookmark() {
+                        Some(bookmark) => format!("Bokmärke: {}", bookmark.chapter),
+                        None => String::from("Inga bokmärken"),
+                    };
                 }
                 KeyCode::Enter => {
+                    if active_menu_item == MenuItem::Home && session.is_none() {
+                        if let Some(selected) = list_state_campaigns.selected() {
+                            let name = selected.checked_sub(1).map(|i| campaigns[i].clone());
+                            status = match storage::open_campaign(name.as_deref()) {
+                                Ok(()) => {
+                                    //Everything held in memory belongs to the old campaign
+                                    ship = read_ship_db().expect("can fetch ship");
+                                    ship_combat = ShipCombat::new(
+                                        &ship,
+                                        &read_module_db().expect("can fetch list"),
+                                    );
+                                    journal = read_journal_db().expect("can fetch journal");
+                                    journal_scroll = 0;
+                                    party = read_party_db().expect("can fetch party");
+                                    darkness =
+                                        read_darkness_db().expect("can fetch darkness points");
+                                    show_power_popup = false;
+                                    faction_target = 0;
+                                    lore.set_chapters(
+                                        lorebook::load(name.as_deref()).expect("can read lore"),
+                                    );
+                                    list_state.select(Some(0));
+                                    list_state_skills.select(Some(0));
+                                    active_campaign = name;
+                                    format!(
+                                        "Kampanj: {}",
+                                        active_campaign.as_deref().unwrap_or("Standard")
+                                    )
+                                }
+                                Err(e) => e.to_string(),
+                            };
+                        }
+                    }
                     if active_menu_item == MenuItem::Character {
                         show_skill_popup = !show_skill_popup;
                     }
+                    if active_menu_item == MenuItem::Ship {
+                        if let Some(selected) = list_state_ship.selected() {
+                            let characters = read_character_db().expect("can fetch list");
+                            if let Some(roll) =
+                                ship_combat.perform(&mut rand::thread_rng(), selected, &characters)
+                            {
+                                journal.record(EntryKind::Roll, roll);
+                                write_db(JOURNAL_DB, &journal)?;
+                            }
+                        }
+                    }
+                    if active_menu_item == MenuItem::Shipyard {
+                        if let Some(selected) = list_state_modules.selected() {
+                            let catalogue = read_module_db().expect("can fetch list");
+                            let module = &catalogue[selected];
+                            if ship.module_ids.contains(&module.id) {
+                                ship.remove(module.id);
+                                shipyard_message = format!("{} borttagen", module.name);
+                            } else {
+                                shipyard_message = match ship.install(module, &catalogue) {
+                                    Ok(()) => {
+                                        journal.record(
+                                            EntryKind::Purchase,
+                                            format!("{}, {} birr", module.name, module.cost),
+                                        );
+                                        write_db(JOURNAL_DB, &journal)?;
+                                        format!("{} installerad", module.name)
+                                    }
+                                    Err(e) => e.to_string(),
+                                };
+                            }
+                            write_db(SHIP_DB, &ship)?;
+                            ship_combat.energy_max = ship.energy_free(&catalogue);
+                        }
+                    }
+                    if active_menu_item == MenuItem::Travel {
+                        if let Some(selected) = list_state_systems.selected() {
+                            travel_planner.to = Some(horizon.systems[selected].id);
+                            travel_planner.plan(&mut rand::thread_rng(), &horizon);
+                        }
+                    }
+                    if active_menu_item == MenuItem::Encounters && screen == Screen::Gm {
+                        if encounter_generator.show_popup {
+                            encounter_generator.show_popup = false;
+                        } else if let Some(selected) = list_state_encounters.selected() {
+                            let id = root_tables(&encounter_tables)[selected].id.clone();
+                            encounter_generator.roll(&encounter_tables, &id);
+                        }
+                    }
                     if active_menu_item == MenuItem::Items {
                         if let Some(selected) = list_state.selected() {
                             let amount_items = read_item_db().expect("can fetch list").len();
@@ -576,7 +1596,31 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }
                 _ => {}
             },
-            Event::Tick => {}
+            Event::Tick => {
+                //Tables held in memory are read again when the host sends them
+                while let Some(update) = session.as_ref().and_then(|c| c.updates.try_recv().ok()) {
+                    match update {
+                        Update::Table { table, .. } if table == storage::table_name(JOURNAL_DB) => {
+                            journal = read_journal_db().expect("can fetch journal");
+                        }
+                        Update::Table { table, .. } if table == storage::table_name(PARTY_DB) => {
+                            party = read_party_db().expect("can fetch party");
+                        }
+                        Update::Table { table, .. } if table == storage::table_name(SHIP_DB) => {
+                            ship = read_ship_db().expect("can fetch ship");
+                        }
+                        Update::Dice(roll) => {
+                            roll_result = roll.summary();
+                            status = roll.summary();
+                        }
+                        update => {
+                            if let Some(summary) = update.summary() {
+                                status = summary;
+                            }
+                        }
+                    }
+                }
+            }
         }
     }
     println!("Number of skill db reads: {:?}", skillcounter);
@@ -594,14 +1638,10 @@ fn render_popup<B: Backend>(rect: &mut Frame<B>, list_state: &ListState, char_sk
         }
     }
 
-    let selected_skill = skill_char
-        .get(
-            list_state
-                .selected()
-                .expect("there is always a selected skill"),
-        )
-        .expect("exists")
-        .clone();
+    let selected_skill = match list_state.selected().and_then(|i| skill_char.get(i)) {
+        Some(skill) => skill.clone(),
+        None => return,
+    };
 
     let size = rect.size();
     let style = Style::default()
@@ -618,6 +1658,48 @@ fn render_popup<B: Backend>(rect: &mut Frame<B>, list_state: &ListState, char_sk
     rect.render_widget(pop_up, area);
 }
 
+//Every skill in sheet order, allmänna first, with its dice pool
+fn roll_options(character: &Character) -> Vec<SkillTotal> {
+    let (allmanna, kvalificerade) = skill_totals(character);
+    allmanna.into_iter().chain(kvalificerade).collect()
+}
+
+fn render_roll_popup<B: Backend>(
+    rect: &mut Frame<B>,
+    character: &Character,
+    list_state: &mut ListState,
+    result: &str,
+) {
+    let area = centered_rect(64, 60, rect.size());
+    let block = Block::default()
+        .title(Span::styled(
+            format!("{} slår (Enter slår, Esc stänger)", character.name),
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        ))
+        .borders(Borders::ALL);
+    let inner = block.inner(area);
+    rect.render_widget(Clear, area);
+    rect.render_widget(block, area);
+    let items: Vec<ListItem> = roll_options(character)
+        .into_iter()
+        .map(|(skill, _, dice)| ListItem::new(format!("{:<24}{} tärningar", skill, dice)))
+        .collect();
+    let halves = Layout::default()
+        .constraints([Constraint::Min(3), Constraint::Length(3)].as_ref())
+        .split(inner);
+    rect.render_stateful_widget(
+        List::new(items).highlight_style(Style::default().bg(Color::Yellow).fg(Color::Black)),
+        halves[0],
+        list_state,
+    );
+    rect.render_widget(
+        Paragraph::new(result.to_string()).wrap(Wrap { trim: true }),
+        halves[1],
+    );
+}
+
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let popup_layout = Layout::default()
         .direction(Direction::Vertical)
@@ -660,9 +1742,8 @@ fn render_home<'a>() -> (Paragraph<'a>, Paragraph<'a>) {
     (banner_para, home)
 }
 
-fn render_character<'a>(
-    list_state: &mut ListState,
-) -> (
+//list, details, grundegenskaper, färdigheter and the skill/weapon/gear/armor ids
+type CharacterView<'a> = (
     List<'a>,
     Table<'a>,
     Table<'a>,
@@ -671,7 +1752,18 @@ fn render_character<'a>(
     Vec<usize>,
     Vec<usize>,
     Vec<usize>,
-) {
+);
+
+//A gm_only character selected on the player screen
+fn hidden_character(list_state: &ListState, screen: Screen) -> bool {
+    let characters = read_character_db().expect("can fetch list");
+    list_state
+        .selected()
+        .and_then(|i| characters.get(i))
+        .is_some_and(|c| !screen.shows(c.gm_only))
+}
+
+fn render_character<'a>(list_state: &mut ListState, screen: Screen) -> CharacterView<'a> {
     let character = Block::default()
         .borders(Borders::ALL)
         .style(Style::default().fg(Color::White))
@@ -702,6 +1794,15 @@ fn render_character<'a>(
         )
         .expect("exists")
         .clone();
+    //The player screen shows an NPC's name and background but not what it can do
+    let hidden = !screen.shows(selected_character.gm_only);
+    let stat = |value: u8| {
+        if hidden {
+            String::from("?")
+        } else {
+            value.to_string()
+        }
+    };
 
     let list = List::new(items).block(character).highlight_style(
         Style::default()
@@ -713,27 +1814,19 @@ fn render_character<'a>(
     let grundegenskaper_table = Table::new(vec![
         Row::new(vec![
             Cell::from("Styrka"),
-            Cell::from(Span::raw(
-                selected_character.grundegenskaper.styrka.to_string(),
-            )),
+            Cell::from(Span::raw(stat(selected_character.grundegenskaper.styrka))),
         ]),
         Row::new(vec![
             Cell::from("Kyla"),
-            Cell::from(Span::raw(
-                selected_character.grundegenskaper.kyla.to_string(),
-            )),
+            Cell::from(Span::raw(stat(selected_character.grundegenskaper.kyla))),
         ]),
         Row::new(vec![
             Cell::from("Skärpa"),
-            Cell::from(Span::raw(
-                selected_character.grundegenskaper.skärpa.to_string(),
-            )),
+            Cell::from(Span::raw(stat(selected_character.grundegenskaper.skärpa))),
         ]),
         Row::new(vec![
             Cell::from("Känsla"),
-            Cell::from(Span::raw(
-                selected_character.grundegenskaper.känsla.to_string(),
-            )),
+            Cell::from(Span::raw(stat(selected_character.grundegenskaper.känsla))),
         ]),
     ])
     .block(
@@ -745,315 +1838,49 @@ fn render_character<'a>(
     )
     .widths(&[Constraint::Percentage(80), Constraint::Percentage(10)]);
 
-    let fardigheter_table = Table::new(vec![
-        Row::new(vec![
-            Cell::from("Kraftprov (STY)"),
-            Cell::from(Span::raw(
-                selected_character
-                    .fardigheter
-                    .allmanna
-                    .kraftprov
-                    .to_string(),
-            )),
-            Cell::from(Span::raw(" => ")),
-            Cell::from(Span::raw(
-                (selected_character.grundegenskaper.styrka
-                    + selected_character.fardigheter.allmanna.kraftprov)
-                    .to_string(),
-            )),
-        ]),
-        Row::new(vec![
-            Cell::from("Manipulera (KNS)"),
-            Cell::from(Span::raw(
-                selected_character
-                    .fardigheter
-                    .allmanna
-                    .manipulera
-                    .to_string(),
-            )),
-            Cell::from(Span::raw(" => ")),
-            Cell::from(Span::raw(
-                (selected_character.grundegenskaper.känsla
-                    + selected_character.fardigheter.allmanna.manipulera)
-                    .to_string(),
-            )),
-        ]),
-        Row::new(vec![
-            Cell::from("Närkamp (STY)"),
-            Cell::from(Span::raw(
-                selected_character.fardigheter.allmanna.närkamp.to_string(),
-            )),
-            Cell::from(Span::raw(" => ")),
-            Cell::from(Span::raw(
-                (selected_character.grundegenskaper.styrka
-                    + selected_character.fardigheter.allmanna.närkamp)
-                    .to_string(),
-            )),
-        ]),
-        Row::new(vec![
-            Cell::from("Rörlighet (KYL)"),
-            Cell::from(Span::raw(
-                selected_character
-                    .fardigheter
-                    .allmanna
-                    .rörlighet
-                    .to_string(),
-            )),
-            Cell::from(Span::raw(" => ")),
-            Cell::from(Span::raw(
-                (selected_character.grundegenskaper.kyla
-                    + selected_character.fardigheter.allmanna.rörlighet)
-                    .to_string(),
-            )),
-        ]),
-        Row::new(vec![
-            Cell::from("Skjutvapen (KYL)"),
-            Cell::from(Span::raw(
-                selected_character
-                    .fardigheter
-                    .allmanna
-                    .skjutvapen
-                    .to_string(),
-            )),
-            Cell::from(Span::raw(" => ")),
-            Cell::from(Span::raw(
-                (selected_character.grundegenskaper.kyla
-                    + selected_character.fardigheter.allmanna.skjutvapen)
-                    .to_string(),
-            )),
-        ]),
-        Row::new(vec![
-            Cell::from("Smyga (KYL)"),
-            Cell::from(Span::raw(
-                selected_character.fardigheter.allmanna.smyga.to_string(),
-            )),
-            Cell::from(Span::raw(" => ")),
-            Cell::from(Span::raw(
-                (selected_character.grundegenskaper.kyla
-                    + selected_character.fardigheter.allmanna.smyga)
-                    .to_string(),
-            )),
-        ]),
-        Row::new(vec![
-            Cell::from("Spaning (SKP)"),
-            Cell::from(Span::raw(
-                selected_character.fardigheter.allmanna.spaning.to_string(),
-            )),
-            Cell::from(Span::raw(" => ")),
-            Cell::from(Span::raw(
-                (selected_character.grundegenskaper.skärpa
-                    + selected_character.fardigheter.allmanna.spaning)
-                    .to_string(),
-            )),
-        ]),
-        Row::new(vec![
-            Cell::from("Överlevnad (SKP)"),
-            Cell::from(Span::raw(
-                selected_character
-                    .fardigheter
-                    .allmanna
-                    .överlevnad
-                    .to_string(),
-            )),
-            Cell::from(Span::raw(" => ")),
-            Cell::from(Span::raw(
-                (selected_character.grundegenskaper.skärpa
-                    + selected_character.fardigheter.allmanna.överlevnad)
-                    .to_string(),
-            )),
-        ]),
-        Row::new(vec![Cell::from("- Kvalificerade -")]),
-        Row::new(vec![
-            Cell::from("Befäl (KNS)"),
-            Cell::from(Span::raw(
-                selected_character
-                    .fardigheter
-                    .kvalificerade
-                    .befäl
-                    .to_string(),
-            )),
-            Cell::from(Span::raw(" => ")),
-            Cell::from(Span::raw(
-                if selected_character.fardigheter.kvalificerade.befäl > 0 {
-                    (selected_character.grundegenskaper.känsla
-                        + selected_character.fardigheter.kvalificerade.befäl)
-                        .to_string()
-                } else {
-                    String::from("0")
-                },
-            )),
-        ]),
-        Row::new(vec![
-            Cell::from("Datadjinn (SKP)"),
-            Cell::from(Span::raw(
-                selected_character
-                    .fardigheter
-                    .kvalificerade
-                    .datadjinn
-                    .to_string(),
-            )),
-            Cell::from(Span::raw(" => ")),
-            Cell::from(Span::raw(
-                if selected_character.fardigheter.kvalificerade.datadjinn > 0 {
-                    (selected_character.grundegenskaper.skärpa
-                        + selected_character.fardigheter.kvalificerade.datadjinn)
-                        .to_string()
-                } else {
-                    String::from("0")
-                },
-            )),
-        ]),
-        Row::new(vec![
-            Cell::from("Horisontens kultur (KNS)"),
-            Cell::from(Span::raw(
-                selected_character
-                    .fardigheter
-                    .kvalificerade
-                    .horistonens_kultur
-                    .to_string(),
-            )),
-            Cell::from(Span::raw(" => ")),
-            Cell::from(Span::raw(
-                if selected_character
-                    .fardigheter
-                    .kvalificerade
-                    .horistonens_kultur
-                    > 0
-                {
-                    (selected_character.grundegenskaper.känsla
-                        + selected_character
-                            .fardigheter
-                            .kvalificerade
-                            .horistonens_kultur)
-                        .to_string()
-                } else {
-                    String::from("0")
-                },
-            )),
-        ]),
+    let (allmanna, kvalificerade) = skill_totals(&selected_character);
+    let skill_row = |(name, value, total): SkillTotal| {
         Row::new(vec![
-            Cell::from("Medikurgi (SKP)"),
-            Cell::from(Span::raw(
-                selected_character
-                    .fardigheter
-                    .kvalificerade
-                    .medikrugi
-                    .to_string(),
-            )),
-            Cell::from(Span::raw(" => ")),
-            Cell::from(Span::raw(
-                if selected_character.fardigheter.kvalificerade.medikrugi > 0 {
-                    (selected_character.grundegenskaper.skärpa
-                        + selected_character.fardigheter.kvalificerade.medikrugi)
-                        .to_string()
-                } else {
-                    String::from("0")
-                },
-            )),
-        ]),
-        Row::new(vec![
-            Cell::from("Mystiska krafter (KNS)"),
-            Cell::from(Span::raw(
-                selected_character
-                    .fardigheter
-                    .kvalificerade
-                    .mystiska_krafter
-                    .to_string(),
-            )),
-            Cell::from(Span::raw(" => ")),
-            Cell::from(Span::raw(
-                if selected_character
-                    .fardigheter
-                    .kvalificerade
-                    .mystiska_krafter
-                    > 0
-                {
-                    (selected_character.grundegenskaper.känsla
-                        + selected_character
-                            .fardigheter
-                            .kvalificerade
-                            .mystiska_krafter)
-                        .to_string()
-                } else {
-                    String::from("0")
-                },
-            )),
-        ]),
-        Row::new(vec![
-            Cell::from("Pilot (KYL)"),
-            Cell::from(Span::raw(
-                selected_character
-                    .fardigheter
-                    .kvalificerade
-                    .pilot
-                    .to_string(),
-            )),
+            Cell::from(name),
+            Cell::from(Span::raw(stat(value))),
             Cell::from(Span::raw(" => ")),
-            Cell::from(Span::raw(
-                if selected_character.fardigheter.kvalificerade.pilot > 0 {
-                    (selected_character.grundegenskaper.kyla
-                        + selected_character.fardigheter.kvalificerade.pilot)
-                        .to_string()
-                } else {
-                    String::from("0")
-                },
-            )),
-        ]),
-        Row::new(vec![
-            Cell::from("Teknologi (SKP)"),
-            Cell::from(Span::raw(
-                selected_character
-                    .fardigheter
-                    .kvalificerade
-                    .teknologi
-                    .to_string(),
-            )),
-            Cell::from(Span::raw(" => ")),
-            Cell::from(Span::raw(
-                if selected_character.fardigheter.kvalificerade.teknologi > 0 {
-                    (selected_character.grundegenskaper.skärpa
-                        + selected_character.fardigheter.kvalificerade.teknologi)
-                        .to_string()
-                } else {
-                    String::from("0")
-                },
-            )),
-        ]),
-        Row::new(vec![
-            Cell::from("Vetenskap (SKP)"),
-            Cell::from(Span::raw(
-                selected_character
-                    .fardigheter
-                    .kvalificerade
-                    .vetenskap
-                    .to_string(),
-            )),
-            Cell::from(Span::raw(" => ")),
-            Cell::from(Span::raw(
-                if selected_character.fardigheter.kvalificerade.vetenskap > 0 {
-                    (selected_character.grundegenskaper.skärpa
-                        + selected_character.fardigheter.kvalificerade.vetenskap)
-                        .to_string()
-                } else {
-                    String::from("0")
-                },
-            )),
-        ]),
-    ])
-    .block(
-        Block::default()
-            .borders(Borders::ALL)
-            .style(Style::default().fg(Color::White))
-            .title("Färdigheter")
-            .border_type(BorderType::Plain),
-    )
-    .widths(&[
-        Constraint::Percentage(30),
-        Constraint::Percentage(2),
-        Constraint::Percentage(5),
-        Constraint::Percentage(5),
-    ]);
+            Cell::from(Span::raw(stat(total))),
+        ])
+    };
+    let mut fardigheter_rows: Vec<Row> = allmanna.into_iter().map(skill_row).collect();
+    fardigheter_rows.push(Row::new(vec![Cell::from("- Kvalificerade -")]));
+    fardigheter_rows.extend(kvalificerade.into_iter().map(skill_row));
+    let fardigheter_table = Table::new(fardigheter_rows)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default().fg(Color::White))
+                .title("Färdigheter")
+                .border_type(BorderType::Plain),
+        )
+        .widths(&[
+            Constraint::Percentage(30),
+            Constraint::Percentage(2),
+            Constraint::Percentage(5),
+            Constraint::Percentage(5),
+        ]);
 
+    //An icon missing from icons.json is shown in red, without a talent
+    let icons = read_icon_db().expect("can fetch icons");
+    let skills = read_skill_db().expect("can fetch list");
+    let (icon_cell, icon_talent) = match icon::find(&icons, &selected_character.icon) {
+        Some(icon) => (
+            Cell::from(Span::raw(icon.name.clone())),
+            icon::talent(icon, &skills).map_or(String::new(), |t| t.name.clone()),
+        ),
+        None => (
+            Cell::from(Span::styled(
+                format!("{} (okänd ikon)", selected_character.icon),
+                Style::default().fg(Color::Red),
+            )),
+            String::new(),
+        ),
+    };
     let character_detail = Table::new(vec![
         Row::new(vec![
             Cell::from(Span::raw("Klass: ")),
@@ -1075,9 +1902,10 @@ fn render_character<'a>(
             Cell::from(Span::raw("Gruppkoncept: ")),
             Cell::from(Span::raw(selected_character.group_concept)),
         ]),
+        Row::new(vec![Cell::from(Span::raw("Ikon: ")), icon_cell]),
         Row::new(vec![
-            Cell::from(Span::raw("Ikon: ")),
-            Cell::from(Span::raw(selected_character.icon)),
+            Cell::from(Span::raw("Ikontalang: ")),
+            Cell::from(Span::raw(icon_talent)),
         ]),
         Row::new(vec![
             Cell::from(Span::raw("Problem: ")),
@@ -1085,7 +1913,11 @@ fn render_character<'a>(
         ]),
         Row::new(vec![
             Cell::from(Span::raw("Birr: ")),
-            Cell::from(Span::raw(selected_character.birr.to_string())),
+            Cell::from(Span::raw(if hidden {
+                String::from("?")
+            } else {
+                selected_character.birr.to_string()
+            })),
         ]),
         Row::new(vec![Cell::from(Span::raw("\n\n"))]),
         Row::new(vec![Cell::from(Span::raw("Utseende\n "))]),
@@ -1107,15 +1939,57 @@ fn render_character<'a>(
     )
     .widths(&[Constraint::Percentage(15), Constraint::Percentage(80)]);
 
+    let gear = |ids: Vec<usize>| if hidden { Vec::new() } else { ids };
     (
         list,
         character_detail,
         grundegenskaper_table,
         fardigheter_table,
-        selected_character.skill_ids,
-        selected_character.weapon_ids,
-        selected_character.gear_ids,
-        selected_character.armor_ids,
+        gear(selected_character.skill_ids),
+        gear(selected_character.weapon_ids),
+        gear(selected_character.gear_ids),
+        gear(selected_character.armor_ids),
+    )
+}
+
+//Skill name, skill level and dice pool (attribute + skill)
+type SkillTotal = (&'static str, u8, u8);
+
+//Allmänna and kvalificerade skills with their totals, in character sheet order.
+//Kvalificerade skills give no dice until the character has trained them.
+fn skill_totals(character: &Character) -> (Vec<SkillTotal>, Vec<SkillTotal>) {
+    let g = &character.grundegenskaper;
+    let a = &character.fardigheter.allmanna;
+    let k = &character.fardigheter.kvalificerade;
+    let allmanna = vec![
+        ("Kraftprov (STY)", a.kraftprov, g.styrka),
+        ("Manipulera (KNS)", a.manipulera, g.känsla),
+        ("Närkamp (STY)", a.närkamp, g.styrka),
+        ("Rörlighet (KYL)", a.rörlighet, g.kyla),
+        ("Skjutvapen (KYL)", a.skjutvapen, g.kyla),
+        ("Smyga (KYL)", a.smyga, g.kyla),
+        ("Spaning (SKP)", a.spaning, g.skärpa),
+        ("Överlevnad (SKP)", a.överlevnad, g.skärpa),
+    ];
+    let kvalificerade = vec![
+        ("Befäl (KNS)", k.befäl, g.känsla),
+        ("Datadjinn (SKP)", k.datadjinn, g.skärpa),
+        ("Horisontens kultur (KNS)", k.horistonens_kultur, g.känsla),
+        ("Medikurgi (SKP)", k.medikrugi, g.skärpa),
+        ("Mystiska krafter (KNS)", k.mystiska_krafter, g.känsla),
+        ("Pilot (KYL)", k.pilot, g.kyla),
+        ("Teknologi (SKP)", k.teknologi, g.skärpa),
+        ("Vetenskap (SKP)", k.vetenskap, g.skärpa),
+    ];
+    (
+        allmanna
+            .into_iter()
+            .map(|(name, skill, attribute)| (name, skill, skill_pool(attribute, skill, false)))
+            .collect(),
+        kvalificerade
+            .into_iter()
+            .map(|(name, skill, attribute)| (name, skill, skill_pool(attribute, skill, true)))
+            .collect(),
     )
 }
 
@@ -1145,10 +2019,10 @@ fn render_skills<'a>(list_state: &mut ListState) -> (List<'a>, Paragraph<'a>) {
                         .add_modifier(Modifier::BOLD),
                 )]))
             } else {
-                ListItem::new(Spans::from(vec![Span::styled(
-                    skill.name.clone(),
-                    Style::default(),
-                )]))
+                ListItem::new(Spans::from(vec![
+                    Span::styled(skill.name.clone(), Style::default()),
+                    source_span(&skill.source),
+                ]))
             }
         })
         .collect();
@@ -1175,7 +2049,10 @@ fn render_skills<'a>(list_state: &mut ListState) -> (List<'a>, Paragraph<'a>) {
             Block::default()
                 .borders(Borders::ALL)
                 .style(Style::default().fg(Color::White))
-                .title(selected_skill.name)
+                .title(Spans::from(vec![
+                    Span::raw(selected_skill.name),
+                    source_span(&selected_skill.source),
+                ]))
                 .border_type(BorderType::Plain),
         );
 
@@ -1184,7 +2061,7 @@ fn render_skills<'a>(list_state: &mut ListState) -> (List<'a>, Paragraph<'a>) {
 
 fn render_char_skills<'a>(
     list_state: &mut ListState,
-    char_skills: &Vec<usize>,
+    char_skills: &[usize],
 ) -> (List<'a>, Paragraph<'a>) {
     let skills = Block::default()
         .borders(Borders::ALL)
@@ -1200,6 +2077,19 @@ fn render_char_skills<'a>(
             skill_char.push(skill);
         }
     }
+    let list = |items: Vec<ListItem<'a>>| {
+        List::new(items).block(skills).highlight_style(
+            Style::default()
+                .bg(Color::Yellow)
+                .fg(Color::Black)
+                .add_modifier(Modifier::BOLD),
+        )
+    };
+    //Hidden characters and characters without talents have nothing to select
+    if skill_char.is_empty() {
+        list_state.select(None);
+        return (list(Vec::new()), Paragraph::new(""));
+    }
     let skill_list_len = skill_char.len() - 1;
     let items: Vec<_> = skill_char
         .iter()
@@ -1211,7 +2101,7 @@ fn render_char_skills<'a>(
         })
         .collect();
     //Checks index boundary, sets zero if out of bounds.
-    if list_state.selected().unwrap() > skill_list_len {
+    if list_state.selected().is_none_or(|i| i > skill_list_len) {
         list_state.select(Some(0));
     }
     let selected_skill = skill_char
@@ -1223,12 +2113,7 @@ fn render_char_skills<'a>(
         .expect("exists")
         .clone();
 
-    let list = List::new(items).block(skills).highlight_style(
-        Style::default()
-            .bg(Color::Yellow)
-            .fg(Color::Black)
-            .add_modifier(Modifier::BOLD),
-    );
+    let list = list(items);
 
     let skill_detail = Paragraph::new(selected_skill.description).block(
         Block::default()
@@ -1241,6 +2126,37 @@ fn render_char_skills<'a>(
     (list, skill_detail)
 }
 
+#[cfg(test)]
+#[test]
+fn test_render_character_without_talents() {
+    use tui::backend::TestBackend;
+    let mut list_state = ListState::default();
+    let mut list_state_skills = ListState::default();
+    list_state_skills.select(Some(2));
+    let ashar = read_character_db()
+        .unwrap()
+        .iter()
+        .position(|c| c.gm_only)
+        .unwrap();
+    list_state.select(Some(ashar));
+    //The player screen gets no talents for a hidden character
+    let (.., skill_ids, _, _, _) = render_character(&mut list_state, Screen::Player);
+    assert!(skill_ids.is_empty());
+    render_char_skills(&mut list_state_skills, &skill_ids);
+    assert_eq!(list_state_skills.selected(), None);
+    let mut terminal = Terminal::new(TestBackend::new(80, 30)).unwrap();
+    terminal
+        .draw(|rect| render_popup(rect, &list_state_skills, skill_ids.clone()))
+        .unwrap();
+
+    let (.., skill_ids, _, _, _) = render_character(&mut list_state, Screen::Gm);
+    assert_eq!(skill_ids.len(), 3);
+    render_char_skills(&mut list_state_skills, &skill_ids);
+    assert_eq!(list_state_skills.selected(), Some(0));
+    render_char_skills(&mut list_state_skills, &[]);
+    assert_eq!(list_state_skills.selected(), None);
+}
+
 #[allow(dead_code)]
 fn render_character_skills<'a>(char_skills: Vec<usize>) -> Table<'a> {
     let skill_list = read_skill_db().expect("can fetch skill list");
@@ -1249,7 +2165,10 @@ fn render_character_skills<'a>(char_skills: Vec<usize>) -> Table<'a> {
 
     for skill in skill_list {
         if char_skills.contains(&skill.id) {
-            rows.push(Row::new(vec![Cell::from(Span::raw(skill.name))]));
+            rows.push(Row::new(vec![Cell::from(Spans::from(vec![
+                Span::raw(skill.name),
+                source_span(&skill.source),
+            ]))]));
             rows.push(Row::new(vec![Cell::from(Span::raw(skill.description))]));
         }
     }
@@ -1280,6 +2199,7 @@ fn render_character_items<'a>(char_skills: Vec<usize>) -> List<'a> {
         if char_skills.contains(&skill.id) {
             items.push(ListItem::new(Spans::from(vec![
                 (Span::raw(skill.name.clone())),
+                source_span(&skill.source),
             ])));
         }
     }
@@ -1305,10 +2225,10 @@ fn render_items<'a>(list_state: &mut ListState) -> (List<'a>, Paragraph<'a>) {
     let items: Vec<_> = item_list
         .iter()
         .map(|i| {
-            ListItem::new(Spans::from(vec![Span::styled(
-                i.name.clone(),
-                Style::default(),
-            )]))
+            ListItem::new(Spans::from(vec![
+                Span::styled(i.name.clone(), Style::default()),
+                source_span(&i.source),
+            ]))
         })
         .collect();
 
@@ -1332,7 +2252,10 @@ fn render_items<'a>(list_state: &mut ListState) -> (List<'a>, Paragraph<'a>) {
         Block::default()
             .borders(Borders::ALL)
             .style(Style::default().fg(Color::White))
-            .title(selected_item.name)
+            .title(Spans::from(vec![
+                Span::raw(selected_item.name),
+                source_span(&selected_item.source),
+            ]))
             .border_type(BorderType::Plain),
     );
 
@@ -1347,7 +2270,10 @@ fn render_character_weapons<'a>(char_weapons: Vec<usize>) -> Table<'a> {
     for weapon in weapon_list {
         if char_weapons.contains(&weapon.id) {
             rows.push(Row::new(vec![
-                Cell::from(Span::raw(weapon.namn)),
+                Cell::from(Spans::from(vec![
+                    Span::raw(weapon.namn),
+                    source_span(&weapon.source),
+                ])),
                 Cell::from(Span::raw(weapon.bonus.to_string())),
                 Cell::from(Span::raw(weapon.init.to_string())),
                 Cell::from(Span::raw(weapon.skada.to_string())),
@@ -1400,7 +2326,10 @@ fn render_character_armor<'a>(char_armor: Vec<usize>) -> Table<'a> {
     for armor in armor_list {
         if char_armor.contains(&armor.id) {
             rows.push(Row::new(vec![
-                Cell::from(Span::raw(armor.name)),
+                Cell::from(Spans::from(vec![
+                    Span::raw(armor.name),
+                    source_span(&armor.source),
+                ])),
                 Cell::from(Span::raw(armor.rating.to_string())),
                 Cell::from(Span::raw(armor.comment)),
             ]));
@@ -1430,31 +2359,112 @@ fn render_character_armor<'a>(char_armor: Vec<usize>) -> Table<'a> {
 }
 
 fn read_skill_db() -> Result<Vec<Skill>, Error> {
-    let db_content = fs::read_to_string(SKILL_DB)?;
-    let parsed: Vec<Skill> = serde_json::from_str(&db_content)?;
+    let parsed: Vec<Skill> = homebrew::load(SKILL_DB)?;
     Ok(parsed)
 }
 
 fn read_character_db() -> Result<Vec<Character>, Error> {
-    let db_content = fs::read_to_string(CHARACTER_DB)?;
-    let parsed: Vec<Character> = serde_json::from_str(&db_content)?;
+    let parsed: Vec<Character> = storage::load(CHARACTER_DB)?;
     Ok(parsed)
 }
 
 fn read_weapon_db() -> Result<Vec<Weapon>, Error> {
-    let db_content = fs::read_to_string(WEAPON_DB)?;
-    let parsed: Vec<Weapon> = serde_json::from_str(&db_content)?;
+    let parsed: Vec<Weapon> = homebrew::load(WEAPON_DB)?;
     Ok(parsed)
 }
 
 fn read_item_db() -> Result<Vec<Item>, Error> {
-    let db_content = fs::read_to_string(ITEM_DB)?;
-    let parsed: Vec<Item> = serde_json::from_str(&db_content)?;
+    let parsed: Vec<Item> = homebrew::load(ITEM_DB)?;
     Ok(parsed)
 }
 
 fn read_armor_db() -> Result<Vec<Armor>, Error> {
-    let db_content = fs::read_to_string(ARMOR_DB)?;
-    let parsed: Vec<Armor> = serde_json::from_str(&db_content)?;
+    let parsed: Vec<Armor> = homebrew::load(ARMOR_DB)?;
+    Ok(parsed)
+}
+
+fn read_ship_db() -> Result<Ship, Error> {
+    let parsed: Ship = storage::load(SHIP_DB)?;
+    Ok(parsed)
+}
+
+//Writes the character sheet in every format to the export directory
+fn export_sheets(character: &Character) -> Result<(), Error> {
+    let skills = read_skill_db()?;
+    let weapons = read_weapon_db()?;
+    let armor = read_armor_db()?;
+    let items = read_item_db()?;
+    let powers = read_power_db()?.powers;
+    fs::create_dir_all(SHEET_EXPORT_DIR).map_err(Error::WriteDBError)?;
+    for format in [SheetFormat::Markdown, SheetFormat::Html, SheetFormat::Text] {
+        let sheet = render_sheet(
+            character, &skills, &weapons, &armor, &items, &powers, format,
+        );
+        let path = format!(
+            "{}/{}.{}",
+            SHEET_EXPORT_DIR,
+            sheet::file_name(character),
+            format.extension()
+        );
+        fs::write(path, sheet).map_err(Error::WriteDBError)?;
+    }
+    let pdf = pdf::render_pdf(character, &skills, &weapons, &armor, &items, &powers)?;
+    let path = format!("{}/{}.pdf", SHEET_EXPORT_DIR, sheet::file_name(character));
+    fs::write(path, pdf).map_err(Error::WriteDBError)?;
+    Ok(())
+}
+
+//Writes the table behind `path` to the active storage backend
+fn write_db<T: Serialize>(path: &str, content: &T) -> Result<(), Error> {
+    storage::save(path, content)
+}
+
+fn read_module_db() -> Result<Vec<Module>, Error> {
+    let parsed: Vec<Module> = storage::load(MODULE_DB)?;
+    Ok(parsed)
+}
+
+fn read_system_db() -> Result<Horizon, Error> {
+    let parsed: Horizon = storage::load(SYSTEM_DB)?;
+    Ok(parsed)
+}
+
+fn read_encounter_db() -> Result<Vec<EncounterTable>, Error> {
+    let parsed: Vec<EncounterTable> = storage::load(ENCOUNTER_DB)?;
+    Ok(parsed)
+}
+
+fn read_journal_db() -> Result<Journal, Error> {
+    let parsed: Journal = storage::load(JOURNAL_DB)?;
+    Ok(parsed)
+}
+
+fn read_faction_db() -> Result<Vec<Faction>, Error> {
+    let parsed: Vec<Faction> = storage::load(FACTION_DB)?;
+    Ok(parsed)
+}
+
+fn read_icon_db() -> Result<Vec<Icon>, Error> {
+    let parsed: Vec<Icon> = storage::load(ICON_DB)?;
+    Ok(parsed)
+}
+
+fn read_power_db() -> Result<Powers, Error> {
+    let parsed: Powers = storage::load(POWER_DB)?;
+    Ok(parsed)
+}
+
+fn read_darkness_db() -> Result<DarknessPool, Error> {
+    let parsed: DarknessPool = storage::load(DARKNESS_DB)?;
+    Ok(parsed)
+}
+
+fn read_party_db() -> Result<Party, Error> {
+    let parsed: Party = storage::load(PARTY_DB)?;
+    Ok(parsed)
+}
+
+fn read_bookmark_db() -> Result<Vec<Bookmark>, Error> {
+    let parsed: Vec<Bookmark> = storage::load(BOOKMARK_DB)?;
     Ok(parsed)
 }
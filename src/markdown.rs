@@ -0,0 +1,176 @@
+use tui::{
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+};
+
+//The small part of Markdown the lore files use: headings, **bold**, *italic*, lists
+//and paragraphs. Lines of a paragraph are joined, blank lines separate paragraphs.
+
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    match line[level..].strip_prefix(' ') {
+        Some(title) if level > 0 => Some((level, title.trim())),
+        _ => None,
+    }
+}
+
+fn list_item(line: &str) -> Option<(String, &str)> {
+    for bullet in ["- ", "* ", "+ "] {
+        if let Some(item) = line.strip_prefix(bullet) {
+            return Some((String::from("• "), item));
+        }
+    }
+    let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+    match line[digits..].strip_prefix(". ") {
+        Some(item) if digits > 0 => Some((format!("{}. ", &line[..digits]), item)),
+        _ => None,
+    }
+}
+
+//Splits a line into spans at ** (bold) and * or _ (italic) markers
+fn inline(prefix: String, text: &str) -> Spans<'static> {
+    let mut spans = Vec::new();
+    if !prefix.is_empty() {
+        spans.push(Span::styled(prefix, Style::default().fg(Color::Yellow)));
+    }
+    let mut style = Style::default();
+    let mut current = String::new();
+    let chars: Vec<char> = text.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let doubled = chars.get(i + 1) == Some(&c);
+        //snake_case is not emphasis
+        let inside_word = i > 0
+            && chars[i - 1].is_alphanumeric()
+            && chars.get(i + 1).is_some_and(|n| n.is_alphanumeric());
+        let modifier = match c {
+            '*' | '_' if doubled => Some(Modifier::BOLD),
+            '*' => Some(Modifier::ITALIC),
+            '_' if !inside_word => Some(Modifier::ITALIC),
+            _ => None,
+        };
+        match modifier {
+            Some(modifier) => {
+                if !current.is_empty() {
+                    spans.push(Span::styled(std::mem::take(&mut current), style));
+                }
+                style = if style.add_modifier.contains(modifier) {
+                    style.remove_modifier(modifier)
+                } else {
+                    style.add_modifier(modifier)
+                };
+                i += if doubled { 2 } else { 1 };
+            }
+            None => {
+                current.push(c);
+                i += 1;
+            }
+        }
+    }
+    if !current.is_empty() {
+        spans.push(Span::styled(current, style));
+    }
+    Spans::from(spans)
+}
+
+pub fn render(text: &str) -> Vec<Spans<'static>> {
+    let mut lines: Vec<Spans> = Vec::new();
+    let mut paragraph: Vec<String> = Vec::new();
+    let flush = |paragraph: &mut Vec<String>, lines: &mut Vec<Spans>| {
+        if !paragraph.is_empty() {
+            lines.push(inline(String::new(), &paragraph.join(" ")));
+            paragraph.clear();
+        }
+    };
+    for line in text.lines() {
+        let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        if line.is_empty() {
+            flush(&mut paragraph, &mut lines);
+            if lines.last().is_some_and(|l| l.width() > 0) {
+                lines.push(Spans::default());
+            }
+        } else if let Some((_, title)) = heading(&line) {
+            flush(&mut paragraph, &mut lines);
+            lines.push(Spans::from(Span::styled(
+                title.to_string(),
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            )));
+        } else if let Some((bullet, item)) = list_item(&line) {
+            flush(&mut paragraph, &mut lines);
+            lines.push(inline(bullet, item));
+        } else {
+            paragraph.push(line);
+        }
+    }
+    flush(&mut paragraph, &mut lines);
+    while lines.last().is_some_and(|l| l.width() == 0) {
+        lines.pop();
+    }
+    lines
+}
+
+//Level one and two headings start a new chapter, text before the first one is titled
+//with `name`. Returns (title, body) pairs.
+pub fn split_chapters(name: &str, text: &str) -> Vec<(String, String)> {
+    let mut chapters: Vec<(String, String)> = Vec::new();
+    for line in text.lines() {
+        match heading(line.trim()) {
+            Some((level, title)) if level <= 2 => {
+                chapters.push((title.to_string(), String::new()));
+            }
+            _ => {
+                if chapters.is_empty() {
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    chapters.push((name.to_string(), String::new()));
+                }
+                let body = &mut chapters.last_mut().expect("a chapter was just added").1;
+                body.push_str(line);
+                body.push('\n');
+            }
+        }
+    }
+    chapters
+}
+
+#[cfg(test)]
+#[test]
+fn test_render_markdown() {
+    let text = "# Rubrik\n\nEn **fet** och *kursiv* rad\nsom fortsätter, snake_case.\n\n\n- första\n2. andra med _betoning_\n";
+    let lines = render(text);
+    let plain: Vec<String> = lines
+        .iter()
+        .map(|l| l.0.iter().map(|s| s.content.as_ref()).collect())
+        .collect();
+    assert_eq!(
+        plain,
+        vec![
+            "Rubrik",
+            "",
+            "En fet och kursiv rad som fortsätter, snake_case.",
+            "",
+            "• första",
+            "2. andra med betoning",
+        ]
+    );
+    let spans = &lines[2].0;
+    assert_eq!(spans[1].content, "fet");
+    assert!(spans[1].style.add_modifier.contains(Modifier::BOLD));
+    assert_eq!(spans[3].content, "kursiv");
+    assert!(spans[3].style.add_modifier.contains(Modifier::ITALIC));
+    assert!(!spans[4].style.add_modifier.contains(Modifier::ITALIC));
+
+    let chapters = split_chapters("anteckningar", "Inledning\n## Kua\ntext\n### Detalj\n");
+    assert_eq!(
+        chapters[0],
+        ("anteckningar".to_string(), "Inledning\n".to_string())
+    );
+    assert_eq!(
+        chapters[1],
+        ("Kua".to_string(), "text\n### Detalj\n".to_string())
+    );
+}
@@ -0,0 +1,281 @@
+use crate::power::Power;
+use crate::sheet::{sections, Content};
+use crate::{Armor, Character, Item, Skill, Weapon};
+use printpdf::{
+    IndirectFontRef, Line, Mm, PdfDocument, PdfDocumentReference, PdfLayerReference, Point,
+};
+use std::io::Cursor;
+
+//DejaVu Sans is embedded so å, ä and ö render without any system fonts
+const FONT: &[u8] = include_bytes!("../assets/fonts/DejaVuSans.ttf");
+const FONT_BOLD: &[u8] = include_bytes!("../assets/fonts/DejaVuSans-Bold.ttf");
+
+//A4 portrait
+const PAGE_WIDTH: f32 = 210.0;
+const PAGE_HEIGHT: f32 = 297.0;
+const MARGIN: f32 = 15.0;
+const TEXT_SIZE: f32 = 9.0;
+//Average DejaVu Sans glyph width in em, used to estimate line breaks
+const GLYPH_WIDTH: f32 = 0.55;
+const PT_TO_MM: f32 = 0.3528;
+//Space for the key in front of a field value
+const FIELD_KEY_WIDTH: f32 = 28.0;
+
+struct PdfWriter {
+    doc: PdfDocumentReference,
+    layer: PdfLayerReference,
+    font: IndirectFontRef,
+    bold: IndirectFontRef,
+    y: f32,
+    pages: usize,
+}
+
+impl PdfWriter {
+    fn new(title: &str) -> Result<PdfWriter, printpdf::Error> {
+        let (doc, page, layer) =
+            PdfDocument::new(title, Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Rollformulär");
+        let font = doc.add_external_font(Cursor::new(FONT))?;
+        let bold = doc.add_external_font(Cursor::new(FONT_BOLD))?;
+        let layer = doc.get_page(page).get_layer(layer);
+        Ok(PdfWriter {
+            doc,
+            layer,
+            font,
+            bold,
+            y: PAGE_HEIGHT - MARGIN,
+            pages: 1,
+        })
+    }
+
+    //Starts a new page if there is less than `height` mm left
+    fn reserve(&mut self, height: f32) {
+        if self.y - height >= MARGIN {
+            return;
+        }
+        self.pages += 1;
+        let (page, layer) = self.doc.add_page(
+            Mm(PAGE_WIDTH),
+            Mm(PAGE_HEIGHT),
+            format!("Sida {}", self.pages),
+        );
+        self.layer = self.doc.get_page(page).get_layer(layer);
+        self.y = PAGE_HEIGHT - MARGIN;
+    }
+
+    fn line_height(size: f32) -> f32 {
+        size * PT_TO_MM * 1.4
+    }
+
+    fn text(&mut self, x: f32, size: f32, bold: bool, text: &str) {
+        let font = if bold { &self.bold } else { &self.font };
+        self.layer
+            .use_text(text, size, Mm(x), Mm(self.y - size * PT_TO_MM), font);
+    }
+
+    fn rule(&mut self) {
+        let line = Line {
+            points: vec![
+                (Point::new(Mm(MARGIN), Mm(self.y)), false),
+                (Point::new(Mm(PAGE_WIDTH - MARGIN), Mm(self.y)), false),
+            ],
+            is_closed: false,
+        };
+        self.layer.set_outline_thickness(0.5);
+        self.layer.add_line(line);
+    }
+
+    fn heading(&mut self, title: &str) {
+        let size = 12.0;
+        self.reserve(Self::line_height(size) * 3.0);
+        self.y -= 3.0;
+        self.text(MARGIN, size, true, title);
+        self.y -= Self::line_height(size);
+        self.rule();
+        self.y -= 2.0;
+    }
+
+    //Writes `text` in a column of `width` mm, breaking lines between words
+    fn paragraph(&mut self, x: f32, width: f32, size: f32, text: &str) {
+        let max_chars = (width / (size * GLYPH_WIDTH * PT_TO_MM)) as usize;
+        for line in wrap(text, max_chars) {
+            self.reserve(Self::line_height(size));
+            self.text(x, size, false, &line);
+            self.y -= Self::line_height(size);
+        }
+    }
+
+    //Two key/value pairs per row like the boxes on the paper sheet, long values are
+    //wrapped within their half of the page
+    fn fields(&mut self, pair: &[(String, String)]) {
+        let half = (PAGE_WIDTH - 2.0 * MARGIN) / 2.0;
+        let max_chars =
+            ((half - FIELD_KEY_WIDTH - 2.0) / (TEXT_SIZE * GLYPH_WIDTH * PT_TO_MM)) as usize;
+        let values: Vec<Vec<String>> = pair.iter().map(|(_, v)| wrap(v, max_chars)).collect();
+        let height = values.iter().map(Vec::len).max().unwrap_or(0).max(1);
+        for n in 0..height {
+            self.reserve(Self::line_height(TEXT_SIZE));
+            for (i, (key, _)) in pair.iter().enumerate() {
+                let x = MARGIN + i as f32 * half;
+                if n == 0 {
+                    self.text(x, TEXT_SIZE, true, key);
+                }
+                if let Some(line) = values[i].get(n) {
+                    //A single word wider than the column is cut
+                    let line: String = line.chars().take(max_chars).collect();
+                    self.text(x + FIELD_KEY_WIDTH, TEXT_SIZE, false, &line);
+                }
+            }
+            self.y -= Self::line_height(TEXT_SIZE);
+        }
+    }
+
+    //One row of cells at the given column offsets
+    fn row(&mut self, columns: &[f32], cells: &[String], bold: bool) {
+        self.reserve(Self::line_height(TEXT_SIZE));
+        for (i, cell) in cells.iter().enumerate() {
+            let x = MARGIN + columns.get(i).copied().unwrap_or(0.0);
+            let next = columns
+                .get(i + 1)
+                .copied()
+                .unwrap_or(PAGE_WIDTH - 2.0 * MARGIN);
+            let max_chars = ((next - (x - MARGIN)) / (TEXT_SIZE * GLYPH_WIDTH * PT_TO_MM)) as usize;
+            let cell: String = cell.chars().take(max_chars.saturating_sub(1)).collect();
+            self.text(x, TEXT_SIZE, bold, &cell);
+        }
+        self.y -= Self::line_height(TEXT_SIZE);
+    }
+}
+
+fn wrap(text: &str, max_chars: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.chars().count() + word.chars().count() + 1 > max_chars {
+            lines.push(line);
+            line = String::new();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+//Column offsets (mm from the left margin) sized after the longest cell in each column
+fn columns(header: &[&str], rows: &[Vec<String>]) -> Vec<f32> {
+    let usable = PAGE_WIDTH - 2.0 * MARGIN;
+    let lengths: Vec<f32> = (0..header.len())
+        .map(|i| {
+            rows.iter()
+                .map(|r| r[i].chars().count())
+                .chain(std::iter::once(header[i].chars().count()))
+                .max()
+                .unwrap_or(1) as f32
+                + 2.0
+        })
+        .collect();
+    let total: f32 = lengths.iter().sum();
+    let mut x = 0.0;
+    lengths
+        .iter()
+        .map(|l| {
+            let start = x;
+            x += usable * l / total;
+            start
+        })
+        .collect()
+}
+
+pub fn render_pdf(
+    character: &Character,
+    skills: &[Skill],
+    weapons: &[Weapon],
+    armor: &[Armor],
+    items: &[Item],
+    powers: &[Power],
+) -> Result<Vec<u8>, printpdf::Error> {
+    let mut pdf = PdfWriter::new(&character.name)?;
+    pdf.text(MARGIN, 22.0, true, &character.name);
+    pdf.y -= PdfWriter::line_height(22.0);
+    pdf.text(MARGIN, 10.0, false, "Coriolis - rollformulär");
+    pdf.y -= PdfWriter::line_height(10.0);
+
+    let half = (PAGE_WIDTH - 2.0 * MARGIN) / 2.0;
+    for section in sections(character, skills, weapons, armor, items, powers) {
+        pdf.heading(section.title);
+        match section.content {
+            Content::Fields(fields) => {
+                for pair in fields.chunks(2) {
+                    pdf.fields(pair);
+                }
+            }
+            Content::Table(header, rows) => {
+                let columns = columns(&header, &rows);
+                let header: Vec<String> = header.iter().map(|h| h.to_string()).collect();
+                pdf.row(&columns, &header, true);
+                for row in rows.iter() {
+                    pdf.row(&columns, row, false);
+                }
+            }
+            Content::Descriptions(entries) => {
+                for (title, description) in entries {
+                    pdf.reserve(PdfWriter::line_height(TEXT_SIZE) * 2.0);
+                    pdf.text(MARGIN, TEXT_SIZE, true, &title);
+                    pdf.y -= PdfWriter::line_height(TEXT_SIZE);
+                    pdf.paragraph(MARGIN + 4.0, 2.0 * half - 4.0, TEXT_SIZE, &description);
+                    pdf.y -= 1.5;
+                }
+            }
+        }
+    }
+    pdf.doc.save_to_bytes()
+}
+
+#[cfg(test)]
+#[test]
+fn test_wrap() {
+    let lines = wrap("Vår mutkolv ger +2 på manipulera", 12);
+    assert_eq!(lines, vec!["Vår mutkolv", "ger +2 på", "manipulera"]);
+    assert!(wrap("", 10).is_empty());
+}
+
+#[cfg(test)]
+#[test]
+fn test_render_pdf() {
+    use crate::{
+        read_armor_db, read_character_db, read_item_db, read_power_db, read_skill_db,
+        read_weapon_db,
+    };
+    let render = |character: &Character| {
+        render_pdf(
+            character,
+            &read_skill_db().unwrap(),
+            &read_weapon_db().unwrap(),
+            &read_armor_db().unwrap(),
+            &read_item_db().unwrap(),
+            &read_power_db().unwrap().powers,
+        )
+        .unwrap()
+    };
+    let contains = |pdf: &[u8], needle: &[u8]| pdf.windows(needle.len()).any(|w| w == needle);
+    let pages = |pdf: &[u8]| pdf.windows(11).filter(|w| w == b"/Type/Page/").count();
+
+    let mut character = read_character_db().unwrap()[0].clone();
+    character.name = String::from("Åsa Öhrström");
+    character.background = String::from("Född på Kua, växte upp bland pärlfiskare");
+    character.appearance.face = String::from("Ärrad käke, gröna ögon");
+    let pdf = render(&character);
+    assert!(pdf.starts_with(b"%PDF"));
+    assert!(contains(&pdf, b"DejaVuSans"));
+    assert!(contains(&pdf, b"DejaVuSans-Bold"));
+    let short = pages(&pdf);
+    assert!(short >= 1);
+
+    //A field value far wider than its column is wrapped onto more lines and pages
+    character.background = "pärlfiskare ".repeat(2000);
+    assert!(pages(&render(&character)) > short);
+}
@@ -0,0 +1,209 @@
+use crate::dice::{roll_pool, skill_pool, Roll};
+use crate::screen::Screen;
+use crate::{centered_rect, Character};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tui::{
+    backend::Backend,
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
+    Frame,
+};
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Power {
+    pub id: usize,
+    pub name: String,
+    pub description: String,
+    //When and how the power can be used
+    pub activation: String,
+    //Darkness Points the GM gets every time the power is activated
+    pub darkness: u8,
+}
+
+//An entry in the mystic side effect table, rolled on a failed activation
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Mishap {
+    pub roll: u8,
+    pub text: String,
+    //Extra Darkness Points on top of the activation
+    #[serde(default)]
+    pub darkness: u8,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Powers {
+    pub powers: Vec<Power>,
+    pub mishaps: Vec<Mishap>,
+}
+
+impl Powers {
+    pub fn known<'a>(&'a self, character: &Character) -> Vec<&'a Power> {
+        self.powers
+            .iter()
+            .filter(|p| character.power_ids.contains(&p.id))
+            .collect()
+    }
+}
+
+//Känsla + Mystiska krafter, which is a qualified skill
+pub fn activation_pool(character: &Character) -> u8 {
+    skill_pool(
+        character.grundegenskaper.känsla,
+        character.fardigheter.kvalificerade.mystiska_krafter,
+        true,
+    )
+}
+
+#[derive(Clone, Debug)]
+pub struct Activation {
+    pub roll: Roll,
+    pub darkness: u8,
+    pub mishap: Option<String>,
+}
+
+impl Activation {
+    //The Darkness Points only for the GM screen
+    pub fn summary(&self, character: &Character, power: &Power, screen: Screen) -> String {
+        let mut text = format!(
+            "{}: {} {:?} => {} framgångar",
+            character.name,
+            power.name,
+            self.roll.dice,
+            self.roll.successes()
+        );
+        if screen == Screen::Gm {
+            text.push_str(&format!(", +{} mörkerpoäng", self.darkness));
+        }
+        if let Some(mishap) = &self.mishap {
+            text.push_str(&format!(", bieffekt: {}", mishap));
+        }
+        text
+    }
+}
+
+//Activating always gives the GM Darkness Points. Without a success the mystic side
+//effect table is rolled with a D6.
+pub fn activate<R: Rng>(
+    rng: &mut R,
+    character: &Character,
+    power: &Power,
+    mishaps: &[Mishap],
+) -> Activation {
+    let roll = roll_pool(rng, activation_pool(character));
+    let mut darkness = power.darkness;
+    let mut mishap = None;
+    if roll.successes() == 0 {
+        let side_effect = rng.gen_range(1..=6);
+        if let Some(m) = mishaps.iter().find(|m| m.roll == side_effect) {
+            darkness += m.darkness;
+            mishap = Some(m.text.clone());
+        }
+    }
+    Activation {
+        roll,
+        darkness,
+        mishap,
+    }
+}
+
+pub fn render_power_popup<B: Backend>(
+    rect: &mut Frame<B>,
+    character: &Character,
+    known: &[&Power],
+    list_state: &mut ListState,
+    result: &str,
+) {
+    let area = centered_rect(64, 50, rect.size());
+    let block = Block::default()
+        .title(Span::styled(
+            format!(
+                "Mystiska krafter, {} tärningar (Enter aktiverar, Esc stänger)",
+                activation_pool(character)
+            ),
+            Style::default()
+                .fg(Color::Magenta)
+                .add_modifier(Modifier::BOLD),
+        ))
+        .borders(Borders::ALL);
+    let inner = block.inner(area);
+    rect.render_widget(Clear, area);
+    rect.render_widget(block, area);
+
+    let items: Vec<ListItem> = known
+        .iter()
+        .map(|p| {
+            ListItem::new(vec![
+                Spans::from(vec![
+                    Span::styled(
+                        p.name.clone(),
+                        Style::default().add_modifier(Modifier::BOLD),
+                    ),
+                    Span::styled(
+                        format!(" +{} mörkerpoäng", p.darkness),
+                        Style::default().fg(Color::Red),
+                    ),
+                ]),
+                Spans::from(p.activation.clone()),
+            ])
+        })
+        .collect();
+    let list =
+        List::new(items).highlight_style(Style::default().bg(Color::Yellow).fg(Color::Black));
+    let halves = Layout::default()
+        .constraints([Constraint::Min(3), Constraint::Length(4)].as_ref())
+        .split(inner);
+    if known.is_empty() {
+        rect.render_widget(
+            Paragraph::new(format!("{} har inga mystiska krafter", character.name)),
+            halves[0],
+        );
+    } else {
+        rect.render_stateful_widget(list, halves[0], list_state);
+    }
+    rect.render_widget(
+        Paragraph::new(result.to_string()).wrap(Wrap { trim: true }),
+        halves[1],
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn test_activate_power() {
+    use rand::{rngs::StdRng, SeedableRng};
+    let powers = crate::read_power_db().unwrap();
+    assert_eq!(powers.mishaps.len(), 6);
+    let mut character = crate::read_character_db().unwrap()[0].clone();
+    character.power_ids = vec![powers.powers[0].id];
+    assert_eq!(powers.known(&character).len(), 1);
+
+    //Without training in mystiska krafter there are no dice, so it always fails
+    character.fardigheter.kvalificerade.mystiska_krafter = 0;
+    let mut rng = StdRng::seed_from_u64(3);
+    let power = &powers.powers[0];
+    let activation = activate(&mut rng, &character, power, &powers.mishaps);
+    assert!(activation.roll.dice.is_empty());
+    let mishap = activation.mishap.clone().unwrap();
+    let extra = powers
+        .mishaps
+        .iter()
+        .find(|m| m.text == mishap)
+        .unwrap()
+        .darkness;
+    assert_eq!(activation.darkness, power.darkness + extra);
+    let points = format!("+{} mörkerpoäng", activation.darkness);
+    assert!(activation
+        .summary(&character, power, Screen::Gm)
+        .contains(&points));
+    assert!(!activation
+        .summary(&character, power, Screen::Player)
+        .contains(&points));
+
+    character.fardigheter.kvalificerade.mystiska_krafter = 2;
+    assert_eq!(
+        activation_pool(&character),
+        character.grundegenskaper.känsla + 2
+    );
+}
@@ -0,0 +1,143 @@
+use crate::dice::Roll;
+use crate::import::resolve;
+use crate::screen::Screen;
+use crate::sheet::{render_sheet, SheetFormat};
+use crate::{
+    read_armor_db, read_character_db, read_item_db, read_power_db, read_skill_db, read_weapon_db,
+    Character, Skill, Weapon,
+};
+use serde::Serialize;
+use serde_json::Value;
+use std::cmp::Ordering;
+use thiserror::Error;
+
+//Lookups and plain text answers shared by the query subcommands and the chat bot
+
+#[derive(Error, Debug)]
+pub enum QueryError {
+    #[error("no {0} named {1}")]
+    NotFound(&'static str, String),
+    #[error("{0} has no field {1}")]
+    UnknownField(&'static str, String),
+    #[error(transparent)]
+    Data(#[from] crate::Error),
+}
+
+//Exact name first (ignoring case), then the closest spelling
+pub fn skill(name: &str) -> Result<Skill, QueryError> {
+    let skills = read_skill_db()?;
+    let id = resolve(name, skills.iter().map(|s| (s.id, s.name.as_str())))
+        .ok_or_else(|| QueryError::NotFound("skill", name.to_string()))?
+        .0;
+    Ok(skills
+        .into_iter()
+        .find(|s| s.id == id)
+        .expect("resolved from this list"))
+}
+
+pub fn weapon(name: &str) -> Result<Weapon, QueryError> {
+    let weapons = read_weapon_db()?;
+    let id = resolve(name, weapons.iter().map(|w| (w.id, w.namn.as_str())))
+        .ok_or_else(|| QueryError::NotFound("weapon", name.to_string()))?
+        .0;
+    Ok(weapons
+        .into_iter()
+        .find(|w| w.id == id)
+        .expect("resolved from this list"))
+}
+
+//The characters `screen` may see
+pub fn characters(screen: Screen) -> Result<Vec<Character>, QueryError> {
+    Ok(read_character_db()?
+        .into_iter()
+        .filter(|c| screen.shows(c.gm_only))
+        .collect())
+}
+
+//By id or by name
+pub fn character(who: &str, screen: Screen) -> Result<Character, QueryError> {
+    let characters = characters(screen)?;
+    let id = match who.trim().parse::<usize>() {
+        Ok(id) => Some(id),
+        Err(_) => resolve(who, characters.iter().map(|c| (c.id, c.name.as_str()))).map(|r| r.0),
+    };
+    id.and_then(|id| characters.into_iter().find(|c| c.id == id))
+        .ok_or_else(|| QueryError::NotFound("character", who.to_string()))
+}
+
+//Sorts on any field of the rows, numbers by value and text alphabetically
+pub fn sort_by_field<T: Serialize>(
+    rows: &mut [T],
+    field: &str,
+    table: &'static str,
+) -> Result<(), QueryError> {
+    let key = |row: &T| {
+        serde_json::to_value(row)
+            .map(|v| v[field].clone())
+            .unwrap_or_default()
+    };
+    if rows.first().is_some_and(|r| key(r).is_null()) {
+        return Err(QueryError::UnknownField(table, field.to_string()));
+    }
+    rows.sort_by(|a, b| match (key(a), key(b)) {
+        (Value::Number(a), Value::Number(b)) => a
+            .as_f64()
+            .partial_cmp(&b.as_f64())
+            .unwrap_or(Ordering::Equal),
+        (Value::String(a), Value::String(b)) => a.cmp(&b),
+        (a, b) => a.to_string().cmp(&b.to_string()),
+    });
+    Ok(())
+}
+
+pub fn skill_text(skill: &Skill) -> String {
+    format!("{} ({})\n{}", skill.name, skill.category, skill.description)
+}
+
+pub fn weapon_text(w: &Weapon) -> String {
+    format!(
+        "{}: bonus +{}, init {}, skada {}, krit {}, räckvidd {}, {} birr\n{}",
+        w.namn, w.bonus, w.init, w.skada, w.krit, w.räckvidd, w.kostnad, w.övrigt
+    )
+}
+
+pub fn roll_text(roll: &Roll) -> String {
+    format!(
+        "{} tärningar: {:?} => {} framgångar",
+        roll.dice.len(),
+        roll.dice,
+        roll.successes()
+    )
+}
+
+pub fn sheet_text(character: &Character) -> Result<String, QueryError> {
+    Ok(render_sheet(
+        character,
+        &read_skill_db()?,
+        &read_weapon_db()?,
+        &read_armor_db()?,
+        &read_item_db()?,
+        &read_power_db()?.powers,
+        SheetFormat::Text,
+    ))
+}
+
+#[cfg(test)]
+#[test]
+fn test_queries() {
+    assert_eq!(skill("näsa för birr").unwrap().name, "Näsa för Birr");
+    assert!(matches!(
+        skill("xyzzy"),
+        Err(QueryError::NotFound("skill", _))
+    ));
+    assert_eq!(character("1", Screen::Player).unwrap().name, "Dhakir Kruth");
+    assert!(character("Ashar", Screen::Player).is_err());
+    assert_eq!(character("ashar tehrani", Screen::Gm).unwrap().id, 3);
+
+    let mut weapons = read_weapon_db().unwrap();
+    sort_by_field(&mut weapons, "kostnad", "weapon").unwrap();
+    assert!(weapons.windows(2).all(|w| w[0].kostnad <= w[1].kostnad));
+    sort_by_field(&mut weapons, "namn", "weapon").unwrap();
+    assert!(weapons.windows(2).all(|w| w[0].namn <= w[1].namn));
+    assert!(sort_by_field(&mut weapons, "vikt", "weapon").is_err());
+}
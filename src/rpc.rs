@@ -0,0 +1,226 @@
+use crate::dice::{roll_pool, MAX_POOL};
+use crate::query::{self, QueryError};
+use crate::screen::Screen;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::io::{self, BufRead, BufReader, Write};
+use thiserror::Error;
+
+//JSON-RPC 2.0 for chat bots, one request per line on stdin/stdout or a Unix socket.
+//`skill`, `weapon`, `roll` and `sheet` answer with {"text": ...}; `command` takes a chat
+//message such as "!skill Eldstorm" so a bridge can forward messages as they are.
+
+#[derive(Error, Debug)]
+pub enum RpcError {
+    #[error("parse error: {0}")]
+    Parse(String),
+    #[error("unknown method: {0}")]
+    UnknownMethod(String),
+    #[error("invalid params: {0}")]
+    InvalidParams(String),
+    #[error(transparent)]
+    Query(#[from] QueryError),
+}
+
+impl RpcError {
+    fn code(&self) -> i32 {
+        match self {
+            RpcError::Parse(_) => -32700,
+            RpcError::UnknownMethod(_) => -32601,
+            RpcError::InvalidParams(_) => -32602,
+            RpcError::Query(QueryError::NotFound(..)) => -32001,
+            RpcError::Query(_) => -32000,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Request {
+    //Requests without an id are notifications and get no answer
+    #[serde(default)]
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+//The argument as a string: ["Näsa", "för", "Birr"], {"name": "Näsa för Birr"} or a bare value
+fn argument(params: &Value, key: &str) -> Result<String, RpcError> {
+    let text = |v: &Value| match v {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    };
+    let found = match params {
+        Value::Array(values) => {
+            let words: Vec<String> = values.iter().filter_map(text).collect();
+            Some(words.join(" ")).filter(|w| !w.is_empty())
+        }
+        Value::Object(fields) => fields.get(key).and_then(text),
+        other => text(other),
+    };
+    found.ok_or_else(|| RpcError::InvalidParams(format!("missing {}", key)))
+}
+
+fn roll(pool: &str) -> Result<String, RpcError> {
+    let dice: u8 = pool
+        .trim()
+        .parse()
+        .ok()
+        .filter(|d| *d <= MAX_POOL)
+        .ok_or_else(|| RpcError::InvalidParams(format!("pool must be 0-{}", MAX_POOL)))?;
+    Ok(query::roll_text(&roll_pool(&mut rand::thread_rng(), dice)))
+}
+
+fn call(method: &str, params: &Value) -> Result<String, RpcError> {
+    match method {
+        "skill" => Ok(query::skill_text(&query::skill(&argument(
+            params, "name",
+        )?)?)),
+        "weapon" => Ok(query::weapon_text(&query::weapon(&argument(
+            params, "name",
+        )?)?)),
+        "roll" => roll(&argument(params, "pool")?),
+        //The bot answers players, so NPCs marked gm_only have no sheet
+        "sheet" => {
+            let who = argument(params, "character")?;
+            Ok(query::sheet_text(&query::character(&who, Screen::Player)?)?)
+        }
+        "command" => {
+            let text = argument(params, "text")?;
+            let text = text.trim().trim_start_matches(['!', '/']);
+            let (method, rest) = text.split_once(' ').unwrap_or((text, ""));
+            if method == "command" {
+                return Err(RpcError::UnknownMethod(method.to_string()));
+            }
+            call(method, &Value::String(rest.trim().to_string()))
+        }
+        other => Err(RpcError::UnknownMethod(other.to_string())),
+    }
+}
+
+//The answer to one line, None for notifications
+pub fn respond(line: &str) -> Option<String> {
+    let (id, result) = match serde_json::from_str::<Request>(line) {
+        Ok(request) => (request.id?, call(&request.method, &request.params)),
+        Err(e) => (Value::Null, Err(RpcError::Parse(e.to_string()))),
+    };
+    let response = match result {
+        Ok(text) => json!({"jsonrpc": "2.0", "id": id, "result": {"text": text}}),
+        Err(e) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": {"code": e.code(), "message": e.to_string()}
+        }),
+    };
+    Some(response.to_string())
+}
+
+//Answers every line until the input ends
+pub fn serve<R: BufRead, W: Write>(input: R, mut output: W) -> io::Result<()> {
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = respond(&line) {
+            writeln!(output, "{}", response)?;
+            output.flush()?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn bind(path: &str) -> Result<std::os::unix::net::UnixListener, crate::Error> {
+    use std::os::unix::fs::FileTypeExt;
+    //A socket left over from an earlier run would make bind fail, anything else is
+    //somebody's file
+    if let Ok(metadata) = std::fs::symlink_metadata(path) {
+        if !metadata.file_type().is_socket() {
+            return Err(crate::Error::NotASocket(path.to_string()));
+        }
+        std::fs::remove_file(path).map_err(crate::Error::WriteDBError)?;
+    }
+    std::os::unix::net::UnixListener::bind(path).map_err(crate::Error::NetworkError)
+}
+
+//Every connection to the socket is answered on its own thread
+#[cfg(unix)]
+pub fn listen(path: &str) -> Result<(), crate::Error> {
+    let listener = bind(path)?;
+    for stream in listener.incoming().flatten() {
+        std::thread::spawn(move || {
+            if let Ok(reader) = stream.try_clone() {
+                let _ = serve(BufReader::new(reader), stream);
+            }
+        });
+    }
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+#[test]
+fn test_rpc_over_a_pipe() {
+    use std::os::unix::net::UnixStream;
+    let (bot, mut bridge) = UnixStream::pair().unwrap();
+    let reader = bot.try_clone().unwrap();
+    let server = std::thread::spawn(move || serve(BufReader::new(reader), bot));
+    let requests = [
+        r#"{"jsonrpc":"2.0","id":1,"method":"skill","params":{"name":"eldstorm"}}"#,
+        r#"{"jsonrpc":"2.0","method":"roll","params":[3]}"#,
+        r#"{"jsonrpc":"2.0","id":2,"method":"command","params":{"text":"!roll 4"}}"#,
+        r#"{"jsonrpc":"2.0","id":3,"method":"sheet","params":["Dakota"]}"#,
+        r#"{"jsonrpc":"2.0","id":4,"method":"sheet","params":["3"]}"#,
+        r#"{"jsonrpc":"2.0","id":5,"method":"fly"}"#,
+        r#"inte json"#,
+    ];
+    for request in requests {
+        writeln!(bridge, "{}", request).unwrap();
+    }
+    bridge.shutdown(std::net::Shutdown::Write).unwrap();
+    let responses: Vec<Value> = BufReader::new(&bridge)
+        .lines()
+        .map(|l| serde_json::from_str(&l.unwrap()).unwrap())
+        .collect();
+    server.join().unwrap().unwrap();
+
+    //The notification got no answer
+    assert_eq!(responses.len(), 6);
+    assert!(responses[0]["result"]["text"]
+        .as_str()
+        .unwrap()
+        .starts_with("Eldstorm"));
+    assert!(responses[1]["result"]["text"]
+        .as_str()
+        .unwrap()
+        .starts_with("4 tärningar"));
+    assert!(responses[2]["result"]["text"]
+        .as_str()
+        .unwrap()
+        .contains("Dakota"));
+    //Ashar is an NPC only the GM sees
+    assert_eq!(responses[3]["error"]["code"], -32001);
+    assert_eq!(responses[4]["error"]["code"], -32601);
+    assert_eq!(responses[5]["error"]["code"], -32700);
+    assert_eq!(responses[5]["id"], Value::Null);
+}
+
+#[cfg(all(test, unix))]
+#[test]
+fn test_bind_keeps_other_files() {
+    let dir = std::env::temp_dir().join(format!("rpc-bind-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let notes = dir.join("notes.txt");
+    std::fs::write(&notes, "anteckningar").unwrap();
+    let path = notes.to_str().unwrap();
+    assert!(matches!(bind(path), Err(crate::Error::NotASocket(_))));
+    assert_eq!(std::fs::read_to_string(&notes).unwrap(), "anteckningar");
+
+    //A stale socket is replaced
+    let socket = dir.join("bot.sock");
+    let socket = socket.to_str().unwrap();
+    drop(bind(socket).unwrap());
+    assert!(bind(socket).is_ok());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
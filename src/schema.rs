@@ -0,0 +1,145 @@
+use crate::Error;
+use serde_json::{json, Map, Value};
+
+//Bump together with a new step in STEPS whenever a data file changes shape
+pub const SCHEMA_VERSION: u32 = 2;
+
+//A table as stored, before it is upgraded to SCHEMA_VERSION
+#[derive(Clone, PartialEq, Debug)]
+pub struct Versioned {
+    pub schema_version: u32,
+    pub content: Value,
+}
+
+//Files written before versioning are a bare array or object, that is version 0.
+//Lists are stored as {"schema_version": n, "rows": [...]}, objects get the key added.
+pub fn unwrap(value: Value) -> Versioned {
+    match value {
+        Value::Object(mut map) if map.contains_key("schema_version") => {
+            let schema_version = map
+                .remove("schema_version")
+                .and_then(|v| v.as_u64())
+                .unwrap_or(0) as u32;
+            let content = match map.remove("rows") {
+                Some(rows) if map.is_empty() => rows,
+                Some(rows) => {
+                    map.insert(String::from("rows"), rows);
+                    Value::Object(map)
+                }
+                None => Value::Object(map),
+            };
+            Versioned {
+                schema_version,
+                content,
+            }
+        }
+        value => Versioned {
+            schema_version: 0,
+            content: value,
+        },
+    }
+}
+
+pub fn wrap(content: Value) -> Value {
+    let mut map = Map::new();
+    map.insert(String::from("schema_version"), json!(SCHEMA_VERSION));
+    match content {
+        Value::Object(fields) => map.extend(fields),
+        content => {
+            map.insert(String::from("rows"), content);
+        }
+    }
+    Value::Object(map)
+}
+
+type Step = fn(&str, &mut Value);
+
+//STEPS[n] upgrades a table from version n to n + 1
+const STEPS: [Step; SCHEMA_VERSION as usize] = [to_v1, to_v2];
+
+//Version 1 only introduced schema_version itself
+fn to_v1(_table: &str, _content: &mut Value) {}
+
+//Version 2 added hit points, radiation and history to characters, cost and weight to items
+fn to_v2(table: &str, content: &mut Value) {
+    let rows = content.as_array_mut().into_iter().flatten();
+    for row in rows.filter_map(|r| r.as_object_mut()) {
+        match table {
+            "character" => {
+                let attribute = |name: &str| {
+                    row.get("grundegenskaper")
+                        .and_then(|g| g.get(name))
+                        .and_then(|v| v.as_u64())
+                        .unwrap_or(0)
+                };
+                let hit_points = attribute("styrka") + attribute("kyla");
+                row.entry("hit_points").or_insert(json!(hit_points));
+                row.entry("radiation").or_insert(json!(0));
+                row.entry("history").or_insert(json!([]));
+            }
+            "items" => {
+                row.entry("cost").or_insert(json!(0));
+                row.entry("weight").or_insert(json!(1.0));
+            }
+            _ => {}
+        }
+    }
+}
+
+//Runs the steps between the stored version and SCHEMA_VERSION in order
+pub fn upgrade(table: &str, versioned: Versioned) -> Result<Value, Error> {
+    if versioned.schema_version > SCHEMA_VERSION {
+        return Err(Error::NewerSchema(
+            table.to_string(),
+            versioned.schema_version,
+        ));
+    }
+    let mut content = versioned.content;
+    for step in STEPS.iter().skip(versioned.schema_version as usize) {
+        step(table, &mut content);
+    }
+    Ok(content)
+}
+
+#[cfg(test)]
+#[test]
+fn test_upgrade_from_unversioned() {
+    let legacy = json!([{
+        "id": 1,
+        "name": "Dakota",
+        "grundegenskaper": { "styrka": 3, "kyla": 4, "skärpa": 2, "känsla": 5 }
+    }]);
+    let versioned = unwrap(legacy.clone());
+    assert_eq!(versioned.schema_version, 0);
+    let upgraded = upgrade("character", versioned).unwrap();
+    assert_eq!(upgraded[0]["hit_points"], 7);
+    assert_eq!(upgraded[0]["history"], json!([]));
+
+    let stored = wrap(upgraded.clone());
+    assert_eq!(stored["schema_version"], SCHEMA_VERSION);
+    assert_eq!(
+        unwrap(stored),
+        Versioned {
+            schema_version: SCHEMA_VERSION,
+            content: upgraded
+        }
+    );
+
+    let journal = wrap(json!({"session": 2, "entries": []}));
+    assert_eq!(unwrap(journal).content["session"], 2);
+    let future = Versioned {
+        schema_version: SCHEMA_VERSION + 1,
+        content: legacy,
+    };
+    assert!(upgrade("character", future).is_err());
+}
+
+#[cfg(test)]
+#[test]
+fn test_to_v2_without_attributes() {
+    let mut rows = json!([{ "id": 1, "name": "Tom" }, { "id": 2, "grundegenskaper": 3 }]);
+    to_v2("character", &mut rows);
+    assert_eq!(rows[0]["hit_points"], 0);
+    assert_eq!(rows[0]["radiation"], 0);
+    assert_eq!(rows[1]["hit_points"], 0);
+}
@@ -0,0 +1,54 @@
+//Who is looking at the terminal. On a shared monitor it is the players, the GM starts
+//the program with --gm to see NPC stats, Darkness Points, secret notes and encounter
+//rolls. Data entries marked gm_only are left out of the player screen.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub enum Screen {
+    Gm,
+    #[default]
+    Player,
+}
+
+//What the player screen shows instead of hidden information
+pub const HIDDEN: &str = "Dolt för spelarna";
+
+impl Screen {
+    //Removes --gm from the arguments
+    pub fn from_args(args: &mut Vec<String>) -> Screen {
+        match args.iter().position(|a| a == "--gm") {
+            Some(i) => {
+                args.remove(i);
+                Screen::Gm
+            }
+            None => Screen::Player,
+        }
+    }
+
+    pub fn shows(self, gm_only: bool) -> bool {
+        self == Screen::Gm || !gm_only
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Screen::Gm => "SL-läge",
+            Screen::Player => "Spelarläge",
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_screen_from_args() {
+    let mut args = vec![
+        String::from("--campaign"),
+        String::from("kua"),
+        String::from("--gm"),
+    ];
+    let screen = Screen::from_args(&mut args);
+    assert_eq!(screen, Screen::Gm);
+    assert_eq!(args, vec!["--campaign", "kua"]);
+    assert!(screen.shows(true));
+    let screen = Screen::from_args(&mut args);
+    assert_eq!(screen, Screen::Player);
+    assert!(screen.shows(false));
+    assert!(!screen.shows(true));
+}
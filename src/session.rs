@@ -0,0 +1,847 @@
+use crate::dice::{roll_pool, MAX_POOL, SUCCESS_FACE};
+use crate::schema::{Versioned, SCHEMA_VERSION};
+use crate::storage::{self, Storage};
+use crate::{Error, CHARACTER_DB, DARKNESS_DB, DATA_FILES, JOURNAL_DB};
+use chrono::{DateTime, Local};
+use rand::{rngs::StdRng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+use tui::{
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, BorderType, Borders, Paragraph, Wrap},
+};
+
+//A shared session: `serve` hosts the game database and players `connect` to it. Every
+//message is one line of JSON. The host sends a snapshot of every table when a player
+//joins and pushes updates after that. Players announce themselves and ask the host to
+//roll dice for them.
+
+pub const DEFAULT_PORT: u16 = 7777;
+
+//How often the host looks for changes made in the data files, by the GM's own terminal UI
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+//Events kept for the session panel
+const MAX_EVENTS: usize = 50;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Request {
+    Hello { name: String },
+    //The host rolls in the name the player gave in the hello
+    Roll { skill: String, dice: u8 },
+}
+
+//A roll made by the host, so every player sees the same dice. Rolling `dice` dice with an
+//StdRng seeded with `seed` gives `results` again, see verify.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SharedRoll {
+    pub who: String,
+    pub skill: String,
+    pub dice: u8,
+    pub seed: u64,
+    pub timestamp: DateTime<Local>,
+    pub results: Vec<u8>,
+}
+
+impl SharedRoll {
+    pub fn roll(who: &str, skill: &str, dice: u8, seed: u64) -> SharedRoll {
+        SharedRoll {
+            who: who.to_string(),
+            skill: skill.to_string(),
+            dice,
+            seed,
+            timestamp: Local::now(),
+            results: Self::dice_for(dice, seed),
+        }
+    }
+
+    fn dice_for(dice: u8, seed: u64) -> Vec<u8> {
+        roll_pool(&mut StdRng::seed_from_u64(seed), dice).dice
+    }
+
+    pub fn verify(&self) -> bool {
+        Self::dice_for(self.dice, self.seed) == self.results
+    }
+
+    pub fn successes(&self) -> usize {
+        self.results.iter().filter(|d| **d == SUCCESS_FACE).count()
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "{} slog {} med {} tärningar: {:?} => {} framgångar",
+            self.who,
+            self.skill,
+            self.dice,
+            self.results,
+            self.successes()
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Update {
+    //Every table by name ("skills", "character", ...) and the initiative order
+    Snapshot {
+        tables: BTreeMap<String, Value>,
+        initiative: Vec<String>,
+        #[serde(default)]
+        rolls: Vec<SharedRoll>,
+    },
+    Table {
+        table: String,
+        content: Value,
+    },
+    HitPoints {
+        character_id: usize,
+        name: String,
+        hit_points: u8,
+    },
+    Initiative {
+        order: Vec<String>,
+    },
+    Roll {
+        text: String,
+    },
+    Joined {
+        name: String,
+    },
+    Dice(SharedRoll),
+}
+
+impl Update {
+    //One line for the session panel, None for updates that only change tables
+    pub fn summary(&self) -> Option<String> {
+        match self {
+            Update::Snapshot { .. } | Update::Table { .. } => None,
+            Update::HitPoints {
+                name, hit_points, ..
+            } => Some(format!("{}: {} KP", name, hit_points)),
+            Update::Initiative { order } => Some(format!("Initiativ: {}", order.join(", "))),
+            Update::Roll { text } => Some(text.clone()),
+            Update::Joined { name } => Some(format!("{} anslöt", name)),
+            Update::Dice(roll) => Some(roll.summary()),
+        }
+    }
+}
+
+//What the host and every player know about the session
+#[derive(Default)]
+pub struct SessionState {
+    pub tables: BTreeMap<String, Value>,
+    pub initiative: Vec<String>,
+    //Newest last
+    pub events: Vec<String>,
+    //Every roll of the session, newest last. Rolls are not repeated in events.
+    pub rolls: Vec<SharedRoll>,
+}
+
+impl SessionState {
+    pub fn apply(&mut self, update: &Update) {
+        match update {
+            Update::Snapshot {
+                tables,
+                initiative,
+                rolls,
+            } => {
+                self.tables = tables.clone();
+                self.initiative = initiative.clone();
+                self.rolls = rolls.clone();
+            }
+            Update::Table { table, content } => {
+                self.tables.insert(table.clone(), content.clone());
+            }
+            Update::HitPoints {
+                character_id,
+                hit_points,
+                ..
+            } => {
+                let characters = self
+                    .tables
+                    .get_mut(&storage::table_name(CHARACTER_DB))
+                    .and_then(|t| t.as_array_mut());
+                if let Some(character) = characters
+                    .into_iter()
+                    .flatten()
+                    .find(|c| c["id"] == *character_id)
+                {
+                    character["hit_points"] = Value::from(*hit_points);
+                }
+            }
+            Update::Initiative { order } => self.initiative = order.clone(),
+            Update::Dice(roll) => {
+                self.rolls.push(roll.clone());
+                return;
+            }
+            Update::Roll { .. } | Update::Joined { .. } => {}
+        }
+        if let Some(event) = update.summary() {
+            self.events.push(event);
+            let overflow = self.events.len().saturating_sub(MAX_EVENTS);
+            self.events.drain(..overflow);
+        }
+    }
+}
+
+//Players get no gm_only entries and no Darkness Points
+fn for_players(table: &str, content: &Value) -> Value {
+    if table == storage::table_name(DARKNESS_DB) {
+        return serde_json::json!({ "points": 0, "history": [] });
+    }
+    fn strip(value: &Value) -> Value {
+        match value {
+            Value::Array(items) => Value::Array(
+                items
+                    .iter()
+                    .filter(|i| i["gm_only"] != true)
+                    .map(strip)
+                    .collect(),
+            ),
+            Value::Object(fields) => {
+                Value::Object(fields.iter().map(|(k, v)| (k.clone(), strip(v))).collect())
+            }
+            other => other.clone(),
+        }
+    }
+    strip(content)
+}
+
+fn send(stream: &mut TcpStream, update: &Update) -> io::Result<()> {
+    let line = serde_json::to_string(update)?;
+    writeln!(stream, "{}", line)
+}
+
+struct Player {
+    name: String,
+    stream: TcpStream,
+}
+
+pub struct Server {
+    addr: SocketAddr,
+    state: Arc<Mutex<SessionState>>,
+    players: Arc<Mutex<Vec<Player>>>,
+}
+
+impl Server {
+    //Binds and accepts players in the background
+    pub fn start(addr: &str, tables: BTreeMap<String, Value>) -> Result<Server, Error> {
+        let listener = TcpListener::bind(addr).map_err(Error::NetworkError)?;
+        let server = Server {
+            addr: listener.local_addr().map_err(Error::NetworkError)?,
+            state: Arc::new(Mutex::new(SessionState {
+                tables,
+                ..SessionState::default()
+            })),
+            players: Arc::new(Mutex::new(Vec::new())),
+        };
+        let state = Arc::clone(&server.state);
+        let players = Arc::clone(&server.players);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let state = Arc::clone(&state);
+                let players = Arc::clone(&players);
+                thread::spawn(move || join(stream, &state, &players));
+            }
+        });
+        Ok(server)
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn state(&self) -> MutexGuard<'_, SessionState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn players(&self) -> Vec<String> {
+        let players = self.players.lock().unwrap_or_else(|e| e.into_inner());
+        players.iter().map(|p| p.name.clone()).collect()
+    }
+
+    pub fn publish(&self, update: Update) {
+        publish(&self.state, &self.players, update);
+    }
+
+    //Rolls on the host with a new seed
+    pub fn roll(&self, who: &str, skill: &str, dice: u8) -> SharedRoll {
+        roll(&self.state, &self.players, who, skill, dice)
+    }
+}
+
+//Applies the update on the host and sends it to every player
+fn publish(state: &Mutex<SessionState>, players: &Mutex<Vec<Player>>, update: Update) {
+    let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+    let update = match update {
+        Update::Table { table, content } => {
+            state.apply(&Update::Table {
+                table: table.clone(),
+                content: content.clone(),
+            });
+            Update::Table {
+                content: for_players(&table, &content),
+                table,
+            }
+        }
+        update => {
+            state.apply(&update);
+            update
+        }
+    };
+    //Hit points of a character the players cannot see stay on the host
+    if let Update::HitPoints { character_id, .. } = &update {
+        let hidden = state
+            .tables
+            .get(&storage::table_name(CHARACTER_DB))
+            .and_then(|t| t.as_array())
+            .into_iter()
+            .flatten()
+            .any(|c| c["id"] == *character_id && c["gm_only"] == true);
+        if hidden {
+            return;
+        }
+    }
+    broadcast(players, &update);
+}
+
+fn roll(
+    state: &Mutex<SessionState>,
+    players: &Mutex<Vec<Player>>,
+    who: &str,
+    skill: &str,
+    dice: u8,
+) -> SharedRoll {
+    let roll = SharedRoll::roll(who, skill, dice, rand::random());
+    publish(state, players, Update::Dice(roll.clone()));
+    roll
+}
+
+fn broadcast(players: &Mutex<Vec<Player>>, update: &Update) {
+    let mut players = players.lock().unwrap_or_else(|e| e.into_inner());
+    //Players who left are noticed when sending fails
+    players.retain_mut(|p| send(&mut p.stream, update).is_ok());
+}
+
+//Reads the player's hello, sends the snapshot and adds the player. The state stays locked
+//in between so no update is missed.
+fn join(stream: TcpStream, state: &Mutex<SessionState>, players: &Mutex<Vec<Player>>) {
+    let mut reader = BufReader::new(match stream.try_clone() {
+        Ok(stream) => stream,
+        Err(_) => return,
+    });
+    let mut line = String::new();
+    let name = match reader
+        .read_line(&mut line)
+        .map(|_| serde_json::from_str(&line))
+    {
+        Ok(Ok(Request::Hello { name })) => name,
+        _ => return,
+    };
+    let mut stream = stream;
+    {
+        let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+        let snapshot = Update::Snapshot {
+            tables: state
+                .tables
+                .iter()
+                .map(|(table, content)| (table.clone(), for_players(table, content)))
+                .collect(),
+            initiative: state.initiative.clone(),
+            rolls: state.rolls.clone(),
+        };
+        if send(&mut stream, &snapshot).is_err() {
+            return;
+        }
+        let joined = Update::Joined { name: name.clone() };
+        players
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(Player {
+                name: name.clone(),
+                stream,
+            });
+        state.apply(&joined);
+        broadcast(players, &joined);
+    }
+    for line in reader.lines().map_while(Result::ok) {
+        //Oversized pools would flood the feed
+        if let Ok(Request::Roll { skill, dice }) = serde_json::from_str(&line) {
+            if dice <= MAX_POOL {
+                roll(state, players, &name, &skill, dice);
+            }
+        }
+    }
+}
+
+//A player's connection. The state follows the host, `updates` gets every update as it arrives.
+pub struct Client {
+    pub addr: String,
+    //The name every roll from this player is made in
+    pub name: String,
+    pub state: Arc<Mutex<SessionState>>,
+    pub updates: Receiver<Update>,
+    stream: TcpStream,
+}
+
+impl Client {
+    //Returns once the snapshot has arrived
+    pub fn connect(addr: &str, name: &str) -> Result<Client, Error> {
+        let mut stream = TcpStream::connect(addr).map_err(Error::NetworkError)?;
+        let hello = serde_json::to_string(&Request::Hello {
+            name: name.to_string(),
+        })?;
+        writeln!(stream, "{}", hello).map_err(Error::NetworkError)?;
+        let mut reader = BufReader::new(stream.try_clone().map_err(Error::NetworkError)?);
+        let mut line = String::new();
+        reader.read_line(&mut line).map_err(Error::NetworkError)?;
+        let snapshot: Update = serde_json::from_str(&line)?;
+        if !matches!(snapshot, Update::Snapshot { .. }) {
+            return Err(Error::Protocol(line.trim().to_string()));
+        }
+        let mut state = SessionState::default();
+        state.apply(&snapshot);
+        let state = Arc::new(Mutex::new(state));
+        let (tx, updates) = mpsc::channel();
+        let shared = Arc::clone(&state);
+        thread::spawn(move || {
+            for line in reader.lines().map_while(Result::ok) {
+                if let Ok(update) = serde_json::from_str::<Update>(&line) {
+                    shared
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .apply(&update);
+                    if tx.send(update).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+        Ok(Client {
+            addr: addr.to_string(),
+            name: name.to_string(),
+            state,
+            updates,
+            stream,
+        })
+    }
+
+    //The host rolls, the result comes back as an Update::Dice to every player
+    pub fn roll(&self, skill: &str, dice: u8) -> Result<(), Error> {
+        if dice > MAX_POOL {
+            return Err(Error::PoolTooLarge(dice));
+        }
+        let request = serde_json::to_string(&Request::Roll {
+            skill: skill.to_string(),
+            dice,
+        })?;
+        writeln!(&self.stream, "{}", request).map_err(Error::NetworkError)
+    }
+
+    //The host rolls in the player's name, another character goes with the skill
+    pub fn roll_for(&self, character: &str, skill: &str, dice: u8) -> Result<(), Error> {
+        if character == self.name {
+            self.roll(skill, dice)
+        } else {
+            self.roll(&format!("{} ({})", skill, character), dice)
+        }
+    }
+
+    pub fn storage(&self) -> SessionStorage {
+        SessionStorage {
+            state: Arc::clone(&self.state),
+        }
+    }
+}
+
+//Reads the tables the host sent. Writes only change this player's copy, the next update
+//from the host replaces them.
+pub struct SessionStorage {
+    state: Arc<Mutex<SessionState>>,
+}
+
+impl Storage for SessionStorage {
+    fn read(&self, table: &str) -> Result<Versioned, Error> {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        match state.tables.get(table) {
+            Some(content) => Ok(Versioned {
+                schema_version: SCHEMA_VERSION,
+                content: content.clone(),
+            }),
+            None => Err(Error::MissingTable(table.to_string())),
+        }
+    }
+
+    fn write(&mut self, table: &str, content: &Value) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.tables.insert(table.to_string(), content.clone());
+        Ok(())
+    }
+
+    fn backup(&mut self, table: &str, _old: &Versioned) -> Result<String, Error> {
+        Ok(format!("{} (sessionen sparar inga kopior)", table))
+    }
+}
+
+fn load_tables() -> Result<BTreeMap<String, Value>, Error> {
+    DATA_FILES
+        .iter()
+        .map(|path| Ok((storage::table_name(path), storage::load::<Value>(path)?)))
+        .collect()
+}
+
+//Rolls added to the journal since `old`, players see the ones that are not secret
+fn new_rolls(old: &Value, new: &Value) -> Vec<String> {
+    let seen = old["entries"].as_array().map_or(0, |e| e.len());
+    new["entries"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .skip(seen)
+        .filter(|e| e["kind"] == "Roll" && e["gm_only"] != true)
+        .filter_map(|e| e["text"].as_str().map(String::from))
+        .collect()
+}
+
+const SERVE_HELP: &str = "Kommandon:
+    kp <id> <värde>         sätter en karaktärs kroppspoäng
+    initiativ <namn> ...    sätter initiativordningen, utan namn töms den
+    slå <tärningar> [vad]   slår tärningar som SL, alla ser slaget
+    spelare                 listar anslutna spelare
+    avsluta                 stänger sessionen";
+
+enum Command {
+    HitPoints(usize, u8),
+    Initiative(Vec<String>),
+    Roll(u8, String),
+    Players,
+    Quit,
+}
+
+fn parse_command(line: &str) -> Option<Command> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    match words.as_slice() {
+        ["kp", id, value] => Some(Command::HitPoints(id.parse().ok()?, value.parse().ok()?)),
+        ["initiativ", names @ ..] => Some(Command::Initiative(
+            names.iter().map(|n| n.to_string()).collect(),
+        )),
+        ["slå", dice, what @ ..] => Some(Command::Roll(
+            dice.parse().ok().filter(|d| *d <= MAX_POOL)?,
+            what.join(" "),
+        )),
+        ["spelare"] => Some(Command::Players),
+        ["avsluta"] => Some(Command::Quit),
+        _ => None,
+    }
+}
+
+//Hosts the active data until `avsluta`. The GM types commands, changes made in the data
+//files are picked up and pushed to the players as well.
+pub fn serve(addr: &str) -> Result<(), Error> {
+    let server = Server::start(addr, load_tables()?)?;
+    println!("sessionen körs på {}", server.local_addr());
+    println!("{}", SERVE_HELP);
+    let (tx, commands) = mpsc::channel();
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines().map_while(Result::ok) {
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    let mut printed = 0;
+    //Commands and polling share this thread, so a command's write is never mistaken for
+    //an outside change
+    loop {
+        match commands.recv_timeout(POLL_INTERVAL) {
+            Ok(line) => match parse_command(&line) {
+                Some(Command::HitPoints(id, hit_points)) => {
+                    let name = server
+                        .state()
+                        .tables
+                        .get(&storage::table_name(CHARACTER_DB))
+                        .and_then(|t| t.as_array())
+                        .and_then(|t| t.iter().find(|c| c["id"] == id))
+                        .and_then(|c| c["name"].as_str())
+                        .map(String::from);
+                    match name {
+                        Some(name) => {
+                            server.publish(Update::HitPoints {
+                                character_id: id,
+                                name,
+                                hit_points,
+                            });
+                            let characters =
+                                server.state().tables[&storage::table_name(CHARACTER_DB)].clone();
+                            storage::save(CHARACTER_DB, &characters)?;
+                        }
+                        None => println!("ingen karaktär med id {}", id),
+                    }
+                }
+                Some(Command::Initiative(order)) => server.publish(Update::Initiative { order }),
+                Some(Command::Roll(dice, what)) => {
+                    server.roll("SL", &what, dice);
+                }
+                Some(Command::Players) => println!("{}", server.players().join(", ")),
+                Some(Command::Quit) => return Ok(()),
+                None => println!("{}", SERVE_HELP),
+            },
+            Err(RecvTimeoutError::Timeout) => {
+                let rolls = server.state().rolls.clone();
+                for roll in rolls.iter().skip(printed) {
+                    println!(
+                        "{} {} (seed {})",
+                        roll.timestamp.format("%H:%M:%S"),
+                        roll.summary(),
+                        roll.seed
+                    );
+                }
+                printed = rolls.len();
+                for (table, content) in load_tables()? {
+                    let old = server.state().tables.get(&table).cloned();
+                    if old.as_ref() == Some(&content) {
+                        continue;
+                    }
+                    if table == storage::table_name(JOURNAL_DB) {
+                        let old = old.unwrap_or(Value::Null);
+                        for text in new_rolls(&old, &content) {
+                            server.publish(Update::Roll { text });
+                        }
+                    }
+                    server.publish(Update::Table { table, content });
+                }
+            }
+            //stdin closed, keep hosting until the process is stopped
+            Err(RecvTimeoutError::Disconnected) => thread::sleep(POLL_INTERVAL),
+        }
+    }
+}
+
+pub fn render_session<'a>(client: &Client) -> Paragraph<'a> {
+    let state = client.state.lock().unwrap_or_else(|e| e.into_inner());
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let mut lines = vec![Spans::from(Span::styled("Initiativ", bold))];
+    for (i, name) in state.initiative.iter().enumerate() {
+        lines.push(Spans::from(format!("{}. {}", i + 1, name)));
+    }
+    lines.push(Spans::from(""));
+    lines.push(Spans::from(Span::styled("Händelser", bold)));
+    for event in state.events.iter().rev() {
+        lines.push(Spans::from(event.clone()));
+    }
+    Paragraph::new(lines).wrap(Wrap { trim: true }).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::White))
+            .title(format!("Session {}", client.addr))
+            .border_type(BorderType::Plain),
+    )
+}
+
+//Every roll of the session, newest first, so the latest always fits
+pub fn render_feed<'a>(client: &Client) -> Paragraph<'a> {
+    let state = client.state.lock().unwrap_or_else(|e| e.into_inner());
+    let lines: Vec<Spans> = state
+        .rolls
+        .iter()
+        .rev()
+        .map(|roll| {
+            Spans::from(vec![
+                Span::styled(
+                    format!("{} ", roll.timestamp.format("%H:%M:%S")),
+                    Style::default().fg(Color::DarkGray),
+                ),
+                Span::styled(
+                    format!("{} ", roll.who),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(format!("{} {}T {:?} ", roll.skill, roll.dice, roll.results)),
+                Span::styled(
+                    format!("{} framgångar", roll.successes()),
+                    Style::default().fg(if roll.successes() > 0 {
+                        Color::Green
+                    } else {
+                        Color::Red
+                    }),
+                ),
+                //Each player checks the dice against the seed
+                if roll.verify() {
+                    Span::styled(
+                        format!(" seed {}", roll.seed),
+                        Style::default().fg(Color::DarkGray),
+                    )
+                } else {
+                    Span::styled(" stämmer inte med seed", Style::default().fg(Color::Red))
+                },
+            ])
+        })
+        .collect();
+    Paragraph::new(lines).wrap(Wrap { trim: true }).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::White))
+            .title("Tärningsslag")
+            .border_type(BorderType::Plain),
+    )
+}
+
+#[cfg(test)]
+#[test]
+fn test_session_over_localhost() {
+    let tables = BTreeMap::from([
+        (
+            String::from("character"),
+            serde_json::json!([
+                {"id": 1, "name": "Dakota", "hit_points": 4},
+                {"id": 2, "name": "Ashar", "hit_points": 7, "gm_only": true}
+            ]),
+        ),
+        (
+            String::from("darkness"),
+            serde_json::json!({"points": 3, "history": []}),
+        ),
+    ]);
+    let server = Server::start("127.0.0.1:0", tables).unwrap();
+    let addr = server.local_addr().to_string();
+    let wait = |client: &Client| {
+        client
+            .updates
+            .recv_timeout(Duration::from_secs(5))
+            .expect("an update from the host")
+    };
+
+    let first = Client::connect(&addr, "Dakota").unwrap();
+    assert_eq!(
+        wait(&first),
+        Update::Joined {
+            name: "Dakota".into()
+        }
+    );
+    {
+        let state = first.state.lock().unwrap();
+        assert_eq!(state.tables["character"].as_array().unwrap().len(), 1);
+        assert_eq!(state.tables["darkness"]["points"], 0);
+    }
+
+    server.publish(Update::HitPoints {
+        character_id: 2,
+        name: "Ashar".into(),
+        hit_points: 5,
+    });
+    server.publish(Update::HitPoints {
+        character_id: 1,
+        name: "Dakota".into(),
+        hit_points: 2,
+    });
+    assert!(matches!(
+        wait(&first),
+        Update::HitPoints {
+            character_id: 1,
+            ..
+        }
+    ));
+    server.publish(Update::Initiative {
+        order: vec!["Dakota".into(), "Ashar".into()],
+    });
+    wait(&first);
+    let storage = first.storage();
+    let characters = storage.read("character").unwrap().content;
+    assert_eq!(characters[0]["hit_points"], 2);
+    assert_eq!(server.state().tables["character"][1]["hit_points"], 5);
+
+    //A late player gets the current state in the snapshot
+    let second = Client::connect(&addr, "Dhakir").unwrap();
+    assert_eq!(
+        second.state.lock().unwrap().initiative,
+        vec!["Dakota", "Ashar"]
+    );
+    assert_eq!(
+        wait(&first),
+        Update::Joined {
+            name: "Dhakir".into()
+        }
+    );
+    assert_eq!(server.players(), vec!["Dakota", "Dhakir"]);
+    assert_eq!(
+        first.state.lock().unwrap().events,
+        vec![
+            "Dakota anslöt",
+            "Dakota: 2 KP",
+            "Initiativ: Dakota, Ashar",
+            "Dhakir anslöt"
+        ]
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn test_shared_dice_feed() {
+    let server = Server::start("127.0.0.1:0", BTreeMap::new()).unwrap();
+    let addr = server.local_addr().to_string();
+    let wait = |client: &Client| loop {
+        match client.updates.recv_timeout(Duration::from_secs(5)) {
+            Ok(Update::Dice(roll)) => return roll,
+            Ok(_) => {}
+            Err(e) => panic!("no roll from the host: {}", e),
+        }
+    };
+    let dakota = Client::connect(&addr, "Dakota").unwrap();
+    let dhakir = Client::connect(&addr, "Dhakir").unwrap();
+    dakota.roll("Skjutvapen", 5).unwrap();
+    let roll = wait(&dakota);
+    assert_eq!(wait(&dhakir), roll);
+    assert_eq!(
+        (roll.who.as_str(), roll.skill.as_str()),
+        ("Dakota", "Skjutvapen")
+    );
+    assert_eq!(roll.results.len(), 5);
+    assert!(roll.verify());
+    let mut forged = roll.clone();
+    forged.results[0] = forged.results[0] % 6 + 1;
+    assert!(!forged.verify());
+
+    //A roll is made in the name from the hello, whatever who the player sends
+    writeln!(
+        &dhakir.stream,
+        r#"{{"type":"roll","who":"Dakota","skill":"Skjutvapen","dice":2}}"#
+    )
+    .unwrap();
+    let claimed = wait(&dakota);
+    assert_eq!(wait(&dhakir), claimed);
+    assert_eq!(claimed.who, "Dhakir");
+
+    //Rolling for another character, such as a power activation for an NPC
+    dakota.roll_for("Ashar", "Telekinesi", 4).unwrap();
+    let power = wait(&dhakir);
+    assert_eq!(wait(&dakota), power);
+    assert_eq!(
+        (power.who.as_str(), power.skill.as_str()),
+        ("Dakota", "Telekinesi (Ashar)")
+    );
+
+    //The host ignores pools above MAX_POOL, the next roll is the GM's
+    assert!(dakota.roll("Skjutvapen", 200).is_err());
+    writeln!(
+        &dakota.stream,
+        r#"{{"type":"roll","skill":"Skjutvapen","dice":200}}"#
+    )
+    .unwrap();
+
+    let gm = server.roll("SL", "Bakhåll", 3);
+    assert_eq!(wait(&dhakir), gm);
+    //A late player gets the whole history
+    let late = Client::connect(&addr, "Sen").unwrap();
+    assert_eq!(
+        late.state.lock().unwrap().rolls,
+        vec![roll, claimed, power, gm]
+    );
+}
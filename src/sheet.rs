@@ -0,0 +1,424 @@
+use crate::power::Power;
+use crate::{skill_totals, Armor, Character, Item, Skill, SkillTotal, Weapon};
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum SheetFormat {
+    Markdown,
+    Html,
+    Text,
+}
+
+impl SheetFormat {
+    pub fn parse(format: &str) -> Option<SheetFormat> {
+        match format {
+            "md" | "markdown" => Some(SheetFormat::Markdown),
+            "html" => Some(SheetFormat::Html),
+            "txt" | "text" => Some(SheetFormat::Text),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SheetFormat::Markdown => "md",
+            SheetFormat::Html => "html",
+            SheetFormat::Text => "txt",
+        }
+    }
+}
+
+pub enum Content {
+    Fields(Vec<(String, String)>),
+    Table(Vec<&'static str>, Vec<Vec<String>>),
+    Descriptions(Vec<(String, String)>),
+}
+
+pub struct Section {
+    pub title: &'static str,
+    pub content: Content,
+}
+
+//Sections in the order of the official character sheet
+pub fn sections(
+    character: &Character,
+    skills: &[Skill],
+    weapons: &[Weapon],
+    armor: &[Armor],
+    items: &[Item],
+    powers: &[Power],
+) -> Vec<Section> {
+    let g = &character.grundegenskaper;
+    let (allmanna, kvalificerade) = skill_totals(character);
+    let skill_rows = |rows: Vec<SkillTotal>| {
+        rows.into_iter()
+            .map(|(name, value, total)| {
+                vec![name.to_string(), value.to_string(), total.to_string()]
+            })
+            .collect()
+    };
+    vec![
+        Section {
+            title: "Karaktär",
+            content: Content::Fields(vec![
+                (String::from("Klass"), character.class.clone()),
+                (String::from("Position"), character.ship_position.clone()),
+                (String::from("Bakgrund"), character.background.clone()),
+                (String::from("Uppväxt"), character.upbringing.clone()),
+                (
+                    String::from("Gruppkoncept"),
+                    character.group_concept.clone(),
+                ),
+                (String::from("Ikon"), character.icon.clone()),
+                (String::from("Problem"), character.problem.clone()),
+                (String::from("Erfarenhet"), character.experience.to_string()),
+            ]),
+        },
+        Section {
+            title: "Grundegenskaper",
+            content: Content::Fields(vec![
+                (String::from("Styrka"), g.styrka.to_string()),
+                (String::from("Kyla"), g.kyla.to_string()),
+                (String::from("Skärpa"), g.skärpa.to_string()),
+                (String::from("Känsla"), g.känsla.to_string()),
+            ]),
+        },
+        Section {
+            title: "Allmänna färdigheter",
+            content: Content::Table(vec!["Färdighet", "Nivå", "Tärningar"], skill_rows(allmanna)),
+        },
+        Section {
+            title: "Kvalificerade färdigheter",
+            content: Content::Table(
+                vec!["Färdighet", "Nivå", "Tärningar"],
+                skill_rows(kvalificerade),
+            ),
+        },
+        Section {
+            title: "Talanger",
+            content: Content::Descriptions(
+                skills
+                    .iter()
+                    .filter(|s| character.skill_ids.contains(&s.id))
+                    .map(|s| (s.name.clone(), s.description.clone()))
+                    .collect(),
+            ),
+        },
+        Section {
+            title: "Mystiska krafter",
+            content: Content::Descriptions(
+                powers
+                    .iter()
+                    .filter(|p| character.power_ids.contains(&p.id))
+                    .map(|p| {
+                        let rules = format!(
+                            "{} Aktivering: {} Ger SL {} mörkerpoäng.",
+                            p.description, p.activation, p.darkness
+                        );
+                        (p.name.clone(), rules)
+                    })
+                    .collect(),
+            ),
+        },
+        Section {
+            title: "Vapen",
+            content: Content::Table(
+                vec![
+                    "Vapen",
+                    "Bonus",
+                    "Init",
+                    "Skada",
+                    "Krit",
+                    "Räckvidd",
+                    "Övrigt",
+                ],
+                weapons
+                    .iter()
+                    .filter(|w| character.weapon_ids.contains(&w.id))
+                    .map(|w| {
+                        vec![
+                            w.namn.clone(),
+                            w.bonus.to_string(),
+                            w.init.to_string(),
+                            w.skada.to_string(),
+                            w.krit.to_string(),
+                            w.räckvidd.clone(),
+                            w.övrigt.clone(),
+                        ]
+                    })
+                    .collect(),
+            ),
+        },
+        Section {
+            title: "Rustning",
+            content: Content::Table(
+                vec!["Rustning", "Skydd", "Övrigt"],
+                armor
+                    .iter()
+                    .filter(|a| character.armor_ids.contains(&a.id))
+                    .map(|a| vec![a.name.clone(), a.rating.to_string(), a.comment.clone()])
+                    .collect(),
+            ),
+        },
+        Section {
+            title: "Utrustning",
+            content: Content::Descriptions(
+                items
+                    .iter()
+                    .filter(|i| character.gear_ids.contains(&i.id))
+                    .map(|i| (i.name.clone(), i.description.clone()))
+                    .collect(),
+            ),
+        },
+        Section {
+            title: "Utseende",
+            content: Content::Fields(vec![
+                (String::from("Ansikte"), character.appearance.face.clone()),
+                (
+                    String::from("Kläder"),
+                    character.appearance.clothing.clone(),
+                ),
+            ]),
+        },
+        Section {
+            title: "Birr",
+            content: Content::Fields(vec![(String::from("Birr"), character.birr.to_string())]),
+        },
+    ]
+}
+
+fn to_markdown(name: &str, sections: &[Section]) -> String {
+    let mut out = format!("# {}\n", name);
+    for section in sections {
+        out.push_str(&format!("\n## {}\n\n", section.title));
+        match &section.content {
+            Content::Fields(fields) => {
+                for (key, value) in fields {
+                    out.push_str(&format!("- **{}:** {}\n", key, value));
+                }
+            }
+            Content::Table(header, rows) => {
+                out.push_str(&format!("| {} |\n", header.join(" | ")));
+                out.push_str(&format!("|{}\n", "---|".repeat(header.len())));
+                for row in rows {
+                    out.push_str(&format!("| {} |\n", row.join(" | ")));
+                }
+            }
+            Content::Descriptions(entries) => {
+                for (title, description) in entries {
+                    out.push_str(&format!("**{}**\n\n{}\n\n", title, description));
+                }
+            }
+        }
+    }
+    out
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn to_html(name: &str, sections: &[Section]) -> String {
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html lang=\"sv\">\n<head>\n<meta charset=\"utf-8\">\n<title>{0}</title>\n\
+         <style>\nbody {{ font-family: sans-serif; max-width: 50em; margin: auto; }}\n\
+         table {{ border-collapse: collapse; width: 100%; }}\n\
+         th, td {{ border: 1px solid #444; padding: 0.2em 0.5em; text-align: left; }}\n\
+         section {{ break-inside: avoid; }}\n</style>\n</head>\n<body>\n<h1>{0}</h1>\n",
+        escape_html(name)
+    );
+    for section in sections {
+        out.push_str(&format!(
+            "<section>\n<h2>{}</h2>\n",
+            escape_html(section.title)
+        ));
+        match &section.content {
+            Content::Fields(fields) => {
+                out.push_str("<table>\n");
+                for (key, value) in fields {
+                    out.push_str(&format!(
+                        "<tr><th>{}</th><td>{}</td></tr>\n",
+                        escape_html(key),
+                        escape_html(value)
+                    ));
+                }
+                out.push_str("</table>\n");
+            }
+            Content::Table(header, rows) => {
+                out.push_str("<table>\n<tr>");
+                for cell in header {
+                    out.push_str(&format!("<th>{}</th>", escape_html(cell)));
+                }
+                out.push_str("</tr>\n");
+                for row in rows {
+                    out.push_str("<tr>");
+                    for cell in row {
+                        out.push_str(&format!("<td>{}</td>", escape_html(cell)));
+                    }
+                    out.push_str("</tr>\n");
+                }
+                out.push_str("</table>\n");
+            }
+            Content::Descriptions(entries) => {
+                for (title, description) in entries {
+                    out.push_str(&format!(
+                        "<h3>{}</h3>\n<p>{}</p>\n",
+                        escape_html(title),
+                        escape_html(description)
+                    ));
+                }
+            }
+        }
+        out.push_str("</section>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+fn to_text(name: &str, sections: &[Section]) -> String {
+    let mut out = format!("{}\n{}\n", name, "=".repeat(name.chars().count()));
+    for section in sections {
+        out.push_str(&format!(
+            "\n{}\n{}\n",
+            section.title,
+            "-".repeat(section.title.chars().count())
+        ));
+        match &section.content {
+            Content::Fields(fields) => {
+                let width = fields
+                    .iter()
+                    .map(|(k, _)| k.chars().count())
+                    .max()
+                    .unwrap_or(0);
+                for (key, value) in fields {
+                    out.push_str(&format!("{:<width$}  {}\n", key, value, width = width));
+                }
+            }
+            Content::Table(header, rows) => {
+                let widths: Vec<usize> = (0..header.len())
+                    .map(|i| {
+                        rows.iter()
+                            .map(|r| r[i].chars().count())
+                            .chain(std::iter::once(header[i].chars().count()))
+                            .max()
+                            .unwrap_or(0)
+                    })
+                    .collect();
+                let line = |cells: Vec<&str>| {
+                    cells
+                        .iter()
+                        .zip(widths.iter())
+                        .map(|(c, w)| format!("{:<w$}", c, w = w))
+                        .collect::<Vec<_>>()
+                        .join("  ")
+                        .trim_end()
+                        .to_string()
+                };
+                out.push_str(&format!("{}\n", line(header.clone())));
+                for row in rows {
+                    out.push_str(&format!(
+                        "{}\n",
+                        line(row.iter().map(|c| c.as_str()).collect())
+                    ));
+                }
+            }
+            Content::Descriptions(entries) => {
+                for (title, description) in entries {
+                    out.push_str(&format!("* {}\n  {}\n", title, description));
+                }
+            }
+        }
+    }
+    out
+}
+
+//Export file name without the extension, safe to use as a path component
+pub fn file_name(character: &Character) -> String {
+    let name: String = character
+        .name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{}-{}", character.id, name)
+}
+
+pub fn render_sheet(
+    character: &Character,
+    skills: &[Skill],
+    weapons: &[Weapon],
+    armor: &[Armor],
+    items: &[Item],
+    powers: &[Power],
+    format: SheetFormat,
+) -> String {
+    let sections = sections(character, skills, weapons, armor, items, powers);
+    match format {
+        SheetFormat::Markdown => to_markdown(&character.name, &sections),
+        SheetFormat::Html => to_html(&character.name, &sections),
+        SheetFormat::Text => to_text(&character.name, &sections),
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_render_sheet() {
+    use crate::{read_armor_db, read_character_db, read_item_db, read_skill_db, read_weapon_db};
+    let mut character = read_character_db().unwrap()[0].clone();
+    character.name = String::from("Dhakir <Kruth> & Co");
+    let skills = read_skill_db().unwrap();
+    let talent = skills
+        .iter()
+        .find(|s| character.skill_ids.contains(&s.id))
+        .unwrap();
+    let render = |format| {
+        render_sheet(
+            &character,
+            &skills,
+            &read_weapon_db().unwrap(),
+            &read_armor_db().unwrap(),
+            &read_item_db().unwrap(),
+            &[],
+            format,
+        )
+    };
+
+    let markdown = render(SheetFormat::Markdown);
+    assert!(markdown.starts_with("# Dhakir <Kruth> & Co\n"));
+    for heading in [
+        "## Grundegenskaper",
+        "## Allmänna färdigheter",
+        "## Talanger",
+    ] {
+        assert!(markdown.contains(heading));
+    }
+    //Kyla 5 + Skjutvapen 3
+    assert!(markdown.contains("| Skjutvapen (KYL) | 3 | 8 |"));
+    assert!(markdown.contains(&format!("**{}**\n\n{}", talent.name, talent.description)));
+
+    let html = render(SheetFormat::Html);
+    assert!(html.contains("<h1>Dhakir &lt;Kruth&gt; &amp; Co</h1>"));
+    assert!(!html.contains("<Kruth>"));
+    assert!(html.contains("<h2>Kvalificerade färdigheter</h2>"));
+    assert!(html.contains("<td>Skjutvapen (KYL)</td><td>3</td><td>8</td>"));
+    assert!(html.contains(&format!("<h3>{}</h3>", escape_html(&talent.name))));
+
+    let text = render(SheetFormat::Text);
+    assert!(text.contains("\nTalanger\n--------\n"));
+    assert!(text
+        .lines()
+        .any(|l| l.starts_with("Skjutvapen (KYL)")
+            && l.split_whitespace().rev().take(2).eq(["8", "3"])));
+    assert!(text.contains(&format!("* {}\n  {}", talent.name, talent.description)));
+
+    assert_eq!(file_name(&character), "1-Dhakir__Kruth____Co");
+    character.name = String::from("../Ashar/Tehrani");
+    assert_eq!(file_name(&character), "1-___Ashar_Tehrani");
+}
@@ -0,0 +1,839 @@
+use crate::dice::{roll_pool, skill_pool};
+use crate::Character;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tui::{
+    layout::Constraint,
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, BorderType, Borders, Cell, List, ListItem, Paragraph, Row, Table, Wrap},
+};
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Ship {
+    pub name: String,
+    pub class: String,
+    pub hull_class: u8,
+    pub hull: u8,
+    pub energy: u8,
+    pub module_ids: Vec<usize>,
+    pub debt: u32,
+    pub debt_payment: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Module {
+    pub id: usize,
+    pub name: String,
+    pub category: String,
+    pub slots: u8,
+    pub energy: u8,
+    pub cost: u32,
+    pub upkeep: u32,
+    pub description: String,
+}
+
+pub struct HullClass {
+    pub slots: u8,
+    pub upkeep: u32,
+}
+
+//Module slots and monthly base upkeep per hull class (I-V)
+pub fn hull_class(class: u8) -> HullClass {
+    match class {
+        0 | 1 => HullClass {
+            slots: 4,
+            upkeep: 500,
+        },
+        2 => HullClass {
+            slots: 6,
+            upkeep: 1000,
+        },
+        3 => HullClass {
+            slots: 8,
+            upkeep: 2000,
+        },
+        4 => HullClass {
+            slots: 10,
+            upkeep: 4000,
+        },
+        _ => HullClass {
+            slots: 12,
+            upkeep: 8000,
+        },
+    }
+}
+
+//Shown in the shipyard
+#[derive(Error, Debug, PartialEq)]
+pub enum ModuleError {
+    #[error("Modulen är redan installerad")]
+    AlreadyInstalled,
+    #[error("För få modulplatser: {needed} behövs, {free} lediga")]
+    NoSlots { needed: u8, free: u8 },
+    #[error("För lite energi: {needed} EP behövs, {free} EP lediga")]
+    NoEnergy { needed: u8, free: u8 },
+}
+
+impl Ship {
+    pub fn installed<'a>(&self, catalogue: &'a [Module]) -> Vec<&'a Module> {
+        catalogue
+            .iter()
+            .filter(|m| self.module_ids.contains(&m.id))
+            .collect()
+    }
+
+    pub fn slots_used(&self, catalogue: &[Module]) -> u8 {
+        self.installed(catalogue).iter().map(|m| m.slots).sum()
+    }
+
+    pub fn energy_used(&self, catalogue: &[Module]) -> u8 {
+        self.installed(catalogue).iter().map(|m| m.energy).sum()
+    }
+
+    //What the installed modules leave for combat actions
+    pub fn energy_free(&self, catalogue: &[Module]) -> u8 {
+        self.energy.saturating_sub(self.energy_used(catalogue))
+    }
+
+    //Hull class upkeep + module upkeep + the debt installment
+    pub fn monthly_cost(&self, catalogue: &[Module]) -> u32 {
+        let modules: u32 = self.installed(catalogue).iter().map(|m| m.upkeep).sum();
+        hull_class(self.hull_class).upkeep + modules + self.debt_payment
+    }
+
+    pub fn install(&mut self, module: &Module, catalogue: &[Module]) -> Result<(), ModuleError> {
+        if self.module_ids.contains(&module.id) {
+            return Err(ModuleError::AlreadyInstalled);
+        }
+        let free_slots = hull_class(self.hull_class)
+            .slots
+            .saturating_sub(self.slots_used(catalogue));
+        if module.slots > free_slots {
+            return Err(ModuleError::NoSlots {
+                needed: module.slots,
+                free: free_slots,
+            });
+        }
+        let free_energy = self.energy_free(catalogue);
+        if module.energy > free_energy {
+            return Err(ModuleError::NoEnergy {
+                needed: module.energy,
+                free: free_energy,
+            });
+        }
+        self.module_ids.push(module.id);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, module_id: usize) {
+        self.module_ids.retain(|id| *id != module_id);
+    }
+}
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum ShipSkill {
+    Befal,
+    Teknologi,
+    Pilot,
+    Datadjinn,
+    Skjutvapen,
+}
+
+impl ShipSkill {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ShipSkill::Befal => "Befäl",
+            ShipSkill::Teknologi => "Teknologi",
+            ShipSkill::Pilot => "Pilot",
+            ShipSkill::Datadjinn => "Datadjinn",
+            ShipSkill::Skjutvapen => "Skjutvapen",
+        }
+    }
+
+    //Attribute + skill for the character manning the position
+    pub fn pool(&self, character: &Character) -> u8 {
+        let g = &character.grundegenskaper;
+        let k = &character.fardigheter.kvalificerade;
+        match self {
+            ShipSkill::Befal => skill_pool(g.känsla, k.befäl, true),
+            ShipSkill::Teknologi => skill_pool(g.skärpa, k.teknologi, true),
+            ShipSkill::Pilot => skill_pool(g.kyla, k.pilot, true),
+            ShipSkill::Datadjinn => skill_pool(g.skärpa, k.datadjinn, true),
+            ShipSkill::Skjutvapen => {
+                skill_pool(g.kyla, character.fardigheter.allmanna.skjutvapen, false)
+            }
+        }
+    }
+}
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum Effect {
+    None,
+    RepairHull,
+}
+
+pub struct Action {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub energy: u8,
+    pub effect: Effect,
+}
+
+const ORDER_ACTIONS: [Action; 3] = [
+    Action {
+        name: "Ge order",
+        description: "Varje framgång ger +1 på en annan besättningsmedlems slag den här rundan.",
+        energy: 0,
+        effect: Effect::None,
+    },
+    Action {
+        name: "Samla besättningen",
+        description: "Lugnar besättningen, varje framgång återställer en förlorad moral.",
+        energy: 0,
+        effect: Effect::None,
+    },
+    Action {
+        name: "Kursändring",
+        description: "Beordrar ny kurs och ändrar avståndet till fienden ett steg.",
+        energy: 1,
+        effect: Effect::None,
+    },
+];
+
+const ENGINEER_ACTIONS: [Action; 3] = [
+    Action {
+        name: "Skadekontroll",
+        description: "Varje framgång lagar en skrovpoäng.",
+        energy: 1,
+        effect: Effect::RepairHull,
+    },
+    Action {
+        name: "Överladda",
+        description: "Pressar reaktorn, varje framgång ger +1 på nästa slag för valfri position.",
+        energy: 2,
+        effect: Effect::None,
+    },
+    Action {
+        name: "Reparera modul",
+        description: "Får en skadad modul att fungera igen.",
+        energy: 1,
+        effect: Effect::None,
+    },
+];
+
+const PILOT_ACTIONS: [Action; 3] = [
+    Action {
+        name: "Manöver",
+        description: "Ändrar position i förhållande till fienden.",
+        energy: 1,
+        effect: Effect::None,
+    },
+    Action {
+        name: "Undanmanöver",
+        description: "Varje framgång ger -1 på fiendens attacker den här rundan.",
+        energy: 1,
+        effect: Effect::None,
+    },
+    Action {
+        name: "Fly",
+        description: "Försöker bryta striden och ta sig ur räckhåll.",
+        energy: 2,
+        effect: Effect::None,
+    },
+];
+
+const SENSOR_ACTIONS: [Action; 3] = [
+    Action {
+        name: "Låsa mål",
+        description: "Varje framgång ger +1 på skyttens nästa attack mot målet.",
+        energy: 1,
+        effect: Effect::None,
+    },
+    Action {
+        name: "Störsändning",
+        description: "Stör fiendens sensorer, -1 på fiendens attacker per framgång.",
+        energy: 1,
+        effect: Effect::None,
+    },
+    Action {
+        name: "Sensorsvep",
+        description: "Avslöjar fiendens moduler och skador.",
+        energy: 0,
+        effect: Effect::None,
+    },
+];
+
+const ATTACK_ACTIONS: [Action; 2] = [
+    Action {
+        name: "Avfyra vapen",
+        description: "Attack med ett av skeppets vapen, extra framgångar ökar skadan.",
+        energy: 1,
+        effect: Effect::None,
+    },
+    Action {
+        name: "Avfyra torped",
+        description: "Torpeden slår till först i nästa runda.",
+        energy: 2,
+        effect: Effect::None,
+    },
+];
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum Phase {
+    Order,
+    Engineer,
+    Pilot,
+    Sensor,
+    Attack,
+}
+
+impl Phase {
+    pub const ALL: [Phase; 5] = [
+        Phase::Order,
+        Phase::Engineer,
+        Phase::Pilot,
+        Phase::Sensor,
+        Phase::Attack,
+    ];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Phase::Order => "Befälsfas",
+            Phase::Engineer => "Ingenjörsfas",
+            Phase::Pilot => "Pilotfas",
+            Phase::Sensor => "Signalfas",
+            Phase::Attack => "Attackfas",
+        }
+    }
+
+    //Matches Character.ship_position
+    pub fn position(&self) -> &'static str {
+        match self {
+            Phase::Order => "Kapten",
+            Phase::Engineer => "Ingenjör",
+            Phase::Pilot => "Pilot",
+            Phase::Sensor => "Signalist",
+            Phase::Attack => "Skytt",
+        }
+    }
+
+    pub fn skill(&self) -> ShipSkill {
+        match self {
+            Phase::Order => ShipSkill::Befal,
+            Phase::Engineer => ShipSkill::Teknologi,
+            Phase::Pilot => ShipSkill::Pilot,
+            Phase::Sensor => ShipSkill::Datadjinn,
+            Phase::Attack => ShipSkill::Skjutvapen,
+        }
+    }
+
+    pub fn actions(&self) -> &'static [Action] {
+        match self {
+            Phase::Order => &ORDER_ACTIONS,
+            Phase::Engineer => &ENGINEER_ACTIONS,
+            Phase::Pilot => &PILOT_ACTIONS,
+            Phase::Sensor => &SENSOR_ACTIONS,
+            Phase::Attack => &ATTACK_ACTIONS,
+        }
+    }
+
+    pub fn index(&self) -> usize {
+        Phase::ALL.iter().position(|p| p == self).unwrap_or(0)
+    }
+}
+
+pub struct ShipCombat {
+    pub round: u32,
+    pub phase: Phase,
+    pub hull: u8,
+    pub hull_max: u8,
+    pub energy_max: u8,
+    //Energy points allocated to each position, indexed like Phase::ALL
+    pub allocated: [u8; 5],
+    //Allocated energy used up by actions this round
+    pub spent: [u8; 5],
+    //One action per position and round
+    pub acted: [bool; 5],
+    pub log: Vec<String>,
+}
+
+impl ShipCombat {
+    pub fn new(ship: &Ship, catalogue: &[Module]) -> ShipCombat {
+        ShipCombat {
+            round: 1,
+            phase: Phase::Order,
+            hull: ship.hull,
+            hull_max: ship.hull,
+            energy_max: ship.energy_free(catalogue),
+            allocated: [0; 5],
+            spent: [0; 5],
+            acted: [false; 5],
+            log: Vec::new(),
+        }
+    }
+
+    pub fn energy_free(&self) -> u8 {
+        self.energy_max
+            .saturating_sub(self.allocated.iter().sum::<u8>())
+    }
+
+    pub fn allocate(&mut self) {
+        if self.energy_free() > 0 {
+            self.allocated[self.phase.index()] += 1;
+        }
+    }
+
+    //Spent energy stays spent until the next round
+    pub fn deallocate(&mut self) {
+        let i = self.phase.index();
+        if self.allocated[i] > self.spent[i] {
+            self.allocated[i] -= 1;
+        }
+    }
+
+    //Steps to the next phase, a new round starts after the attack phase
+    pub fn next_phase(&mut self) {
+        let next = (self.phase.index() + 1) % Phase::ALL.len();
+        if next == 0 {
+            self.round += 1;
+            self.allocated = [0; 5];
+            self.spent = [0; 5];
+            self.acted = [false; 5];
+        }
+        self.phase = Phase::ALL[next];
+    }
+
+    pub fn take_damage(&mut self, damage: u8) {
+        self.hull = self.hull.saturating_sub(damage);
+        self.log.push(format!(
+            "Runda {}: skeppet tar {} i skada ({}/{})",
+            self.round, damage, self.hull, self.hull_max
+        ));
+    }
+
+    pub fn crew_member<'a>(&self, characters: &'a [Character]) -> Option<&'a Character> {
+        characters.iter().find(|c| {
+            c.ship_position
+                .to_lowercase()
+                .eq(&self.phase.position().to_lowercase())
+        })
+    }
+
+    //Rolls the position's skill for the selected action and applies the outcome,
+    //returns the log line of the roll if one was made
+    pub fn perform<R: Rng>(
+        &mut self,
+        rng: &mut R,
+        action: usize,
+        characters: &[Character],
+    ) -> Option<String> {
+        let action = self.phase.actions().get(action)?;
+        let i = self.phase.index();
+        if self.acted[i] {
+            self.log.push(format!(
+                "{} har redan agerat den här rundan",
+                self.phase.position()
+            ));
+            return None;
+        }
+        let available = self.allocated[i] - self.spent[i];
+        if available < action.energy {
+            self.log.push(format!(
+                "{} kräver {} energipoäng, {} tilldelade",
+                action.name, action.energy, available
+            ));
+            return None;
+        }
+        let crew = match self.crew_member(characters) {
+            Some(crew) => crew,
+            None => {
+                self.log
+                    .push(format!("Ingen {} ombord", self.phase.position()));
+                return None;
+            }
+        };
+        self.spent[i] += action.energy;
+        self.acted[i] = true;
+        let skill = self.phase.skill();
+        let roll = roll_pool(rng, skill.pool(crew));
+        let successes = roll.successes();
+        let line = format!(
+            "Runda {}: {} - {} ({}) {:?} => {} framgångar",
+            self.round,
+            crew.name,
+            action.name,
+            skill.name(),
+            roll.dice,
+            successes
+        );
+        self.log.push(line.clone());
+        if action.effect == Effect::RepairHull && successes > 0 {
+            self.hull = (self.hull + successes as u8).min(self.hull_max);
+        }
+        Some(line)
+    }
+}
+
+pub fn render_ship_combat<'a>(
+    combat: &ShipCombat,
+    characters: &[Character],
+) -> (List<'a>, List<'a>, Table<'a>, Paragraph<'a>) {
+    let phases: Vec<_> = Phase::ALL
+        .iter()
+        .map(|phase| {
+            let style = if *phase == combat.phase {
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default()
+            };
+            ListItem::new(Spans::from(vec![Span::styled(
+                format!("{} ({})", phase.title(), phase.position()),
+                style,
+            )]))
+        })
+        .collect();
+    let phase_list = List::new(phases).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::White))
+            .title(format!("Runda {}", combat.round))
+            .border_type(BorderType::Plain),
+    );
+
+    let actions: Vec<_> = combat
+        .phase
+        .actions()
+        .iter()
+        .map(|action| {
+            ListItem::new(vec![
+                Spans::from(Span::styled(
+                    format!("{} [{} EP]", action.name, action.energy),
+                    Style::default().add_modifier(Modifier::BOLD),
+                )),
+                Spans::from(Span::raw(action.description)),
+            ])
+        })
+        .collect();
+    let action_list = List::new(actions)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default().fg(Color::White))
+                .title(combat.phase.title())
+                .border_type(BorderType::Plain),
+        )
+        .highlight_style(
+            Style::default()
+                .bg(Color::Yellow)
+                .fg(Color::Black)
+                .add_modifier(Modifier::BOLD),
+        );
+
+    let mut rows = vec![
+        Row::new(vec![
+            Cell::from("Skrov"),
+            Cell::from(format!("{}/{}", combat.hull, combat.hull_max)),
+        ]),
+        Row::new(vec![
+            Cell::from("Energi"),
+            Cell::from(format!(
+                "{}/{} fria",
+                combat.energy_free(),
+                combat.energy_max
+            )),
+        ]),
+    ];
+    for phase in Phase::ALL.iter() {
+        let crew = characters.iter().find(|c| {
+            c.ship_position
+                .to_lowercase()
+                .eq(&phase.position().to_lowercase())
+        });
+        let crew_text = match crew {
+            Some(c) => format!(
+                "{} - {} {} T6",
+                c.name,
+                phase.skill().name(),
+                phase.skill().pool(c)
+            ),
+            None => String::from("-"),
+        };
+        rows.push(Row::new(vec![
+            Cell::from(phase.position()),
+            Cell::from(format!(
+                "{} EP{}",
+                combat.allocated[phase.index()] - combat.spent[phase.index()],
+                if combat.acted[phase.index()] {
+                    " (klar)"
+                } else {
+                    ""
+                }
+            )),
+            Cell::from(crew_text),
+        ]));
+    }
+    let status = Table::new(rows)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default().fg(Color::White))
+                .title("Skeppet")
+                .border_type(BorderType::Plain),
+        )
+        .widths(&[
+            Constraint::Percentage(20),
+            Constraint::Percentage(20),
+            Constraint::Percentage(60),
+        ]);
+
+    let log: Vec<Spans> = combat
+        .log
+        .iter()
+        .rev()
+        .map(|l| Spans::from(Span::raw(l.clone())))
+        .collect();
+    let log = Paragraph::new(log).wrap(Wrap { trim: true }).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::White))
+            .title("Stridslogg")
+            .border_type(BorderType::Plain),
+    );
+
+    (phase_list, action_list, status, log)
+}
+
+pub fn render_shipyard<'a>(
+    ship: &Ship,
+    catalogue: &[Module],
+    selected: Option<usize>,
+    party_birr: u32,
+    message: &str,
+) -> (List<'a>, Paragraph<'a>, Table<'a>) {
+    let items: Vec<_> = catalogue
+        .iter()
+        .map(|module| {
+            let marker = if ship.module_ids.contains(&module.id) {
+                "[x] "
+            } else {
+                "[ ] "
+            };
+            ListItem::new(Spans::from(vec![
+                Span::styled(marker, Style::default().fg(Color::Cyan)),
+                Span::raw(module.name.clone()),
+            ]))
+        })
+        .collect();
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default().fg(Color::White))
+                .title("Moduler")
+                .border_type(BorderType::Plain),
+        )
+        .highlight_style(
+            Style::default()
+                .bg(Color::Yellow)
+                .fg(Color::Black)
+                .add_modifier(Modifier::BOLD),
+        );
+
+    let (title, detail) = match selected.and_then(|i| catalogue.get(i)) {
+        Some(module) => (
+            module.name.clone(),
+            vec![
+                Spans::from(format!("Typ: {}", module.category)),
+                Spans::from(format!("Modulplatser: {}", module.slots)),
+                Spans::from(format!("Energi: {} EP", module.energy)),
+                Spans::from(format!("Kostnad: {} birr", module.cost)),
+                Spans::from(format!("Underhåll: {} birr/månad", module.upkeep)),
+                Spans::from(""),
+                Spans::from(module.description.clone()),
+            ],
+        ),
+        None => (String::from("Modul"), vec![]),
+    };
+    let module_detail = Paragraph::new(detail).wrap(Wrap { trim: true }).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::White))
+            .title(title)
+            .border_type(BorderType::Plain),
+    );
+
+    let monthly = ship.monthly_cost(catalogue);
+    let months = party_birr.checked_div(monthly).unwrap_or(0);
+    let summary = Table::new(vec![
+        Row::new(vec![Cell::from("Skepp"), Cell::from(ship.name.clone())]),
+        Row::new(vec![
+            Cell::from("Skrovklass"),
+            Cell::from(format!("{} ({})", ship.hull_class, ship.class)),
+        ]),
+        Row::new(vec![
+            Cell::from("Modulplatser"),
+            Cell::from(format!(
+                "{}/{}",
+                ship.slots_used(catalogue),
+                hull_class(ship.hull_class).slots
+            )),
+        ]),
+        Row::new(vec![
+            Cell::from("Energi"),
+            Cell::from(format!(
+                "{}/{} EP",
+                ship.energy_used(catalogue),
+                ship.energy
+            )),
+        ]),
+        Row::new(vec![
+            Cell::from("Skuld"),
+            Cell::from(format!("{} birr", ship.debt)),
+        ]),
+        Row::new(vec![
+            Cell::from("Avbetalning"),
+            Cell::from(format!("{} birr/månad", ship.debt_payment)),
+        ]),
+        Row::new(vec![
+            Cell::from("Månadskostnad"),
+            Cell::from(format!("{} birr", monthly)),
+        ]),
+        Row::new(vec![
+            Cell::from("Gruppens birr"),
+            Cell::from(format!("{} birr ({} månader)", party_birr, months)),
+        ]),
+        Row::new(vec![Cell::from("")]),
+        Row::new(vec![Cell::from(Span::styled(
+            message.to_string(),
+            Style::default().fg(Color::Red),
+        ))]),
+    ])
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::White))
+            .title("Varvet")
+            .border_type(BorderType::Plain),
+    )
+    .widths(&[Constraint::Percentage(40), Constraint::Percentage(60)]);
+
+    (list, module_detail, summary)
+}
+
+#[cfg(test)]
+#[test]
+fn test_install_module() {
+    let module = |id, slots, energy| Module {
+        id,
+        name: String::from("Modul"),
+        category: String::from("Test"),
+        slots,
+        energy,
+        cost: 1000,
+        upkeep: 100,
+        description: String::new(),
+    };
+    let catalogue = vec![module(1, 3, 2), module(2, 2, 2), module(3, 1, 4)];
+    let mut ship = Ship {
+        name: String::from("Test"),
+        class: String::from("Kurir"),
+        hull_class: 1,
+        hull: 4,
+        energy: 5,
+        module_ids: vec![],
+        debt: 0,
+        debt_payment: 200,
+    };
+    assert_eq!(ship.install(&catalogue[0], &catalogue), Ok(()));
+    assert_eq!(
+        ship.install(&catalogue[0], &catalogue),
+        Err(ModuleError::AlreadyInstalled)
+    );
+    assert_eq!(
+        ship.install(&catalogue[1], &catalogue),
+        Err(ModuleError::NoSlots { needed: 2, free: 1 })
+    );
+    assert_eq!(
+        ship.install(&catalogue[2], &catalogue),
+        Err(ModuleError::NoEnergy { needed: 4, free: 3 })
+    );
+    assert_eq!(ship.monthly_cost(&catalogue), 500 + 100 + 200);
+    assert_eq!(ShipCombat::new(&ship, &catalogue).energy_max, 3);
+    ship.remove(1);
+    assert_eq!(ship.slots_used(&catalogue), 0);
+}
+
+#[cfg(test)]
+#[test]
+fn test_phase_order() {
+    let ship = Ship {
+        name: String::from("Test"),
+        class: String::from("Kurir"),
+        hull_class: 1,
+        hull: 6,
+        energy: 2,
+        module_ids: vec![],
+        debt: 0,
+        debt_payment: 0,
+    };
+    let mut combat = ShipCombat::new(&ship, &[]);
+    combat.allocate();
+    combat.allocate();
+    combat.allocate();
+    assert_eq!(combat.energy_free(), 0);
+    for _ in Phase::ALL.iter() {
+        combat.next_phase();
+    }
+    assert_eq!(combat.phase, Phase::Order);
+    assert_eq!(combat.round, 2);
+    assert_eq!(combat.energy_free(), 2);
+    combat.take_damage(8);
+    assert_eq!(combat.hull, 0);
+}
+
+#[cfg(test)]
+#[test]
+fn test_perform_energy_and_one_action() {
+    use rand::SeedableRng;
+    let ship = Ship {
+        name: String::from("Test"),
+        class: String::from("Kurir"),
+        hull_class: 1,
+        hull: 6,
+        energy: 2,
+        module_ids: vec![],
+        debt: 0,
+        debt_payment: 0,
+    };
+    let mut characters = crate::read_character_db().unwrap();
+    characters[0].ship_position = String::from("Ingenjör");
+    let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+    let mut combat = ShipCombat::new(&ship, &[]);
+    combat.take_damage(5);
+    combat.next_phase();
+    assert_eq!(combat.phase, Phase::Engineer);
+    //Skadekontroll needs 1 EP
+    assert!(combat.perform(&mut rng, 0, &characters).is_none());
+    combat.allocate();
+    assert!(combat.perform(&mut rng, 0, &characters).is_some());
+    //The energy is used up and the engineer has acted
+    combat.deallocate();
+    assert_eq!(combat.allocated[1], 1);
+    assert_eq!(combat.energy_free(), 1);
+    combat.allocate();
+    assert!(combat.perform(&mut rng, 0, &characters).is_none());
+    assert_eq!(combat.energy_free(), 0);
+    let hull = combat.hull;
+    for _ in Phase::ALL.iter() {
+        combat.next_phase();
+    }
+    assert_eq!(combat.phase, Phase::Engineer);
+    assert_eq!(combat.energy_free(), 2);
+    combat.allocate();
+    assert!(combat.perform(&mut rng, 0, &characters).is_some());
+    assert!(combat.hull >= hull);
+}
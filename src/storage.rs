@@ -0,0 +1,396 @@
+use crate::schema::{self, Versioned, SCHEMA_VERSION};
+use crate::{campaign, formats, Error};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+//When this file exists it is used instead of the JSON files, see migrate-from-json
+pub const SQLITE_DB: &str = "./data/coriolis.db";
+
+//A table is named after its data file without extension: "skills", "ship", ...
+pub trait Storage: Send {
+    //The table as stored, possibly at an older schema version
+    fn read(&self, table: &str) -> Result<Versioned, Error>;
+    //Always writes SCHEMA_VERSION
+    fn write(&mut self, table: &str, content: &Value) -> Result<(), Error>;
+    //Keeps the table as it was before an upgrade, returns where the copy went
+    fn backup(&mut self, table: &str, old: &Versioned) -> Result<String, Error>;
+}
+
+//One JSON, TOML or RON file per table, as the files in ./data
+pub struct FileStorage {
+    dir: PathBuf,
+}
+
+impl FileStorage {
+    pub fn new(dir: impl Into<PathBuf>) -> FileStorage {
+        FileStorage { dir: dir.into() }
+    }
+
+    fn path(&self, table: &str) -> String {
+        self.dir
+            .join(format!("{}.json", table))
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+impl Storage for FileStorage {
+    fn read(&self, table: &str) -> Result<Versioned, Error> {
+        let path = self.path(table);
+        if !formats::locate(&path).exists() {
+            return Err(Error::MissingTable(table.to_string()));
+        }
+        Ok(schema::unwrap(formats::load(&path)?))
+    }
+
+    fn write(&mut self, table: &str, content: &Value) -> Result<(), Error> {
+        formats::save(&self.path(table), &schema::wrap(content.clone()))
+    }
+
+    //Copies the file byte for byte, so comments in TOML and RON files survive
+    fn backup(&mut self, table: &str, old: &Versioned) -> Result<String, Error> {
+        let file = formats::locate(&self.path(table));
+        let backup = format!("{}.v{}.bak", file.display(), old.schema_version);
+        fs::copy(&file, &backup).map_err(Error::WriteDBError)?;
+        Ok(backup)
+    }
+}
+
+//Applied in order, PRAGMA user_version holds how many have run. Append, never edit.
+const MIGRATIONS: [&str; 2] = [
+    //`id` is copied out of the JSON so single entries can be looked up by index
+    "CREATE TABLE tables (name TEXT PRIMARY KEY, list INTEGER NOT NULL);
+     CREATE TABLE documents (name TEXT PRIMARY KEY, data TEXT NOT NULL);
+     CREATE TABLE rows (
+         tbl TEXT NOT NULL,
+         position INTEGER NOT NULL,
+         id INTEGER,
+         data TEXT NOT NULL,
+         PRIMARY KEY (tbl, position)
+     );
+     CREATE INDEX rows_id ON rows (tbl, id);",
+    //Tables copied before schema_version existed are version 0
+    "ALTER TABLE tables ADD COLUMN schema_version INTEGER NOT NULL DEFAULT 0;
+     CREATE TABLE backups (
+         tbl TEXT NOT NULL,
+         schema_version INTEGER NOT NULL,
+         created TEXT NOT NULL,
+         data TEXT NOT NULL
+     );",
+];
+
+//List tables are stored one row per entry, everything else as a single document
+pub struct SqliteStorage {
+    conn: Connection,
+}
+
+impl SqliteStorage {
+    pub fn open(path: &str) -> Result<SqliteStorage, Error> {
+        Self::migrate(Connection::open(path)?)
+    }
+
+    #[cfg(test)]
+    pub fn in_memory() -> Result<SqliteStorage, Error> {
+        Self::migrate(Connection::open_in_memory()?)
+    }
+
+    fn migrate(mut conn: Connection) -> Result<SqliteStorage, Error> {
+        let version: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            let tx = conn.transaction()?;
+            tx.execute_batch(migration)?;
+            tx.pragma_update(None, "user_version", i + 1)?;
+            tx.commit()?;
+        }
+        Ok(SqliteStorage { conn })
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn read(&self, table: &str) -> Result<Versioned, Error> {
+        let found: Option<(bool, u32)> = self
+            .conn
+            .query_row(
+                "SELECT list, schema_version FROM tables WHERE name = ?1",
+                params![table],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        let (list, schema_version) = found.ok_or_else(|| Error::MissingTable(table.to_string()))?;
+        let content = match list {
+            false => {
+                let data: String = self.conn.query_row(
+                    "SELECT data FROM documents WHERE name = ?1",
+                    params![table],
+                    |row| row.get(0),
+                )?;
+                serde_json::from_str(&data)?
+            }
+            true => {
+                let mut statement = self
+                    .conn
+                    .prepare("SELECT data FROM rows WHERE tbl = ?1 ORDER BY position")?;
+                let rows = statement.query_map(params![table], |row| row.get::<_, String>(0))?;
+                let mut values = Vec::new();
+                for data in rows {
+                    values.push(serde_json::from_str(&data?)?);
+                }
+                Value::Array(values)
+            }
+        };
+        Ok(Versioned {
+            schema_version,
+            content,
+        })
+    }
+
+    fn write(&mut self, table: &str, content: &Value) -> Result<(), Error> {
+        let tx = self.conn.transaction()?;
+        tx.execute("DELETE FROM rows WHERE tbl = ?1", params![table])?;
+        tx.execute("DELETE FROM documents WHERE name = ?1", params![table])?;
+        tx.execute(
+            "INSERT OR REPLACE INTO tables (name, list, schema_version) VALUES (?1, ?2, ?3)",
+            params![table, content.is_array(), SCHEMA_VERSION],
+        )?;
+        match content.as_array() {
+            Some(entries) => {
+                let mut insert = tx.prepare(
+                    "INSERT INTO rows (tbl, position, id, data) VALUES (?1, ?2, ?3, ?4)",
+                )?;
+                for (position, entry) in entries.iter().enumerate() {
+                    let id = entry.get("id").and_then(|id| id.as_i64());
+                    insert.execute(params![table, position, id, entry.to_string()])?;
+                }
+            }
+            None => {
+                tx.execute(
+                    "INSERT INTO documents (name, data) VALUES (?1, ?2)",
+                    params![table, content.to_string()],
+                )?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn backup(&mut self, table: &str, old: &Versioned) -> Result<String, Error> {
+        self.conn.execute(
+            "INSERT INTO backups (tbl, schema_version, created, data)
+             VALUES (?1, ?2, datetime('now'), ?3)",
+            params![table, old.schema_version, old.content.to_string()],
+        )?;
+        Ok(format!(
+            "tabellen backups ({} v{})",
+            table, old.schema_version
+        ))
+    }
+}
+
+//A campaign over the shared rules, each table lives in exactly one of them
+pub struct LayeredStorage {
+    campaign: Box<dyn Storage>,
+    rules: Box<dyn Storage>,
+}
+
+impl LayeredStorage {
+    fn layer(&mut self, table: &str) -> &mut dyn Storage {
+        if campaign::is_campaign_table(table) {
+            self.campaign.as_mut()
+        } else {
+            self.rules.as_mut()
+        }
+    }
+}
+
+impl Storage for LayeredStorage {
+    //Campaign tables not created yet fall through to the defaults in the rules
+    fn read(&self, table: &str) -> Result<Versioned, Error> {
+        if campaign::is_campaign_table(table) {
+            match self.campaign.read(table) {
+                Err(Error::MissingTable(_)) => {}
+                result => return result,
+            }
+        }
+        self.rules.read(table)
+    }
+
+    fn write(&mut self, table: &str, content: &Value) -> Result<(), Error> {
+        self.layer(table).write(table, content)
+    }
+
+    fn backup(&mut self, table: &str, old: &Versioned) -> Result<String, Error> {
+        self.layer(table).backup(table, old)
+    }
+}
+
+static BACKEND: Mutex<Option<Box<dyn Storage>>> = Mutex::new(None);
+
+pub fn table_name(path: &str) -> String {
+    Path::new(path)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or(path)
+        .to_string()
+}
+
+//SQLite when the directory has a coriolis.db, otherwise its data files
+fn open_dir(dir: &Path) -> Result<Box<dyn Storage>, Error> {
+    let db = dir.join("coriolis.db");
+    if db.exists() {
+        Ok(Box::new(SqliteStorage::open(&db.to_string_lossy())?))
+    } else {
+        Ok(Box::new(FileStorage::new(dir)))
+    }
+}
+
+fn default_backend() -> Result<Box<dyn Storage>, Error> {
+    open_dir(Path::new(SQLITE_DB).parent().unwrap_or(Path::new(".")))
+}
+
+//Switches every later read and write to a campaign, None goes back to ./data alone
+pub fn open_campaign(name: Option<&str>) -> Result<(), Error> {
+    let backend = match name {
+        Some(name) => Box::new(LayeredStorage {
+            campaign: open_dir(&campaign::dir(name)?)?,
+            rules: default_backend()?,
+        }),
+        None => default_backend()?,
+    };
+    *BACKEND.lock().unwrap_or_else(|e| e.into_inner()) = Some(backend);
+    Ok(())
+}
+
+//Reads and writes go to `backend` from now on, such as a connected session
+pub fn use_backend(backend: Box<dyn Storage>) {
+    *BACKEND.lock().unwrap_or_else(|e| e.into_inner()) = Some(backend);
+}
+
+fn with_backend<R>(f: impl FnOnce(&mut dyn Storage) -> Result<R, Error>) -> Result<R, Error> {
+    let mut backend = BACKEND.lock().unwrap_or_else(|e| e.into_inner());
+    if backend.is_none() {
+        *backend = Some(default_backend()?);
+    }
+    f(backend.as_deref_mut().expect("backend was just set"))
+}
+
+//Reads a table and upgrades it to SCHEMA_VERSION, writing a backup before the upgrade is saved
+fn read_current(storage: &mut dyn Storage, table: &str) -> Result<Value, Error> {
+    let stored = storage.read(table)?;
+    if stored.schema_version == SCHEMA_VERSION {
+        return Ok(stored.content);
+    }
+    let content = schema::upgrade(table, stored.clone())?;
+    storage.backup(table, &stored)?;
+    storage.write(table, &content)?;
+    Ok(content)
+}
+
+//Reads the table behind a data file path such as SKILL_DB from the active backend
+pub fn load<T: DeserializeOwned>(path: &str) -> Result<T, Error> {
+    let value = with_backend(|b| read_current(b, &table_name(path)))?;
+    Ok(serde_json::from_value(value)?)
+}
+
+pub fn save<T: Serialize>(path: &str, content: &T) -> Result<(), Error> {
+    let value = serde_json::to_value(content)?;
+    with_backend(|b| b.write(&table_name(path), &value))
+}
+
+//Copies every table from the files in ./data into a new or existing SQLite file
+pub fn migrate_from_json(paths: &[&str], target: &str) -> Result<usize, Error> {
+    let source = FileStorage::new("./data");
+    let mut sqlite = SqliteStorage::open(target)?;
+    for path in paths {
+        let table = table_name(path);
+        let stored = source.read(&table)?;
+        sqlite.write(&table, &schema::upgrade(&table, stored)?)?;
+    }
+    Ok(paths.len())
+}
+
+//The same checks for every backend, the source tables come from ./data
+#[cfg(test)]
+fn storage_suite(storage: &mut dyn Storage) {
+    let source = FileStorage::new("./data");
+    for path in crate::DATA_FILES {
+        let table = table_name(path);
+        let original = source.read(&table).unwrap().content;
+        storage.write(&table, &original).unwrap();
+        let stored = storage.read(&table).unwrap();
+        assert_eq!(stored.schema_version, SCHEMA_VERSION);
+        assert_eq!(stored.content, original, "{}", table);
+    }
+
+    let mut characters = storage.read("character").unwrap().content;
+    let mut copy = characters[0].clone();
+    copy["id"] = Value::from(99);
+    copy["name"] = Value::from("Kopia");
+    characters.as_array_mut().unwrap().push(copy);
+    storage.write("character", &characters).unwrap();
+    let read: Vec<crate::Character> =
+        serde_json::from_value(storage.read("character").unwrap().content).unwrap();
+    assert_eq!(read.last().map(|c| c.name.as_str()), Some("Kopia"));
+
+    storage
+        .write("journal", &serde_json::json!({"session": 4, "entries": []}))
+        .unwrap();
+    let journal: crate::journal::Journal =
+        serde_json::from_value(storage.read("journal").unwrap().content).unwrap();
+    assert_eq!(journal.session, 4);
+
+    let items = storage.read("items").unwrap();
+    storage.backup("items", &items).unwrap();
+    assert!(storage.read("finns_inte").is_err());
+}
+
+#[cfg(test)]
+#[test]
+fn test_file_storage() {
+    let dir = std::env::temp_dir().join(format!("coriolis-storage-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut storage = FileStorage::new(&dir);
+    storage_suite(&mut storage);
+
+    //An unversioned file is upgraded on first read, after a backup of the old content
+    let legacy = r#"[{"id": 1, "name": "Gift", "description": "Farligt"}]"#;
+    fs::write(dir.join("items.json"), legacy).unwrap();
+    let items = read_current(&mut storage, "items").unwrap();
+    assert_eq!(items[0]["weight"], 1.0);
+    let backup = fs::read_to_string(dir.join("items.json.v0.bak")).unwrap();
+    assert_eq!(backup, legacy);
+    assert_eq!(
+        storage.read("items").unwrap().schema_version,
+        SCHEMA_VERSION
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(test)]
+#[test]
+fn test_sqlite_storage() {
+    storage_suite(&mut SqliteStorage::in_memory().unwrap());
+}
+
+#[cfg(test)]
+#[test]
+fn test_layered_storage() {
+    let dir = std::env::temp_dir().join(format!("coriolis-campaign-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let mut layered = LayeredStorage {
+        campaign: Box::new(FileStorage::new(&dir)),
+        rules: Box::new(FileStorage::new("./data")),
+    };
+    let rules_journal = layered.read("journal").unwrap();
+    let journal = serde_json::json!({"session": 9, "entries": []});
+    layered.write("journal", &journal).unwrap();
+    assert!(dir.join("journal.json").exists());
+    assert_eq!(layered.read("journal").unwrap().content, journal);
+    assert_ne!(layered.read("journal").unwrap(), rules_journal);
+    assert!(layered.read("skills").is_ok());
+    assert!(!dir.join("skills.json").exists());
+    fs::remove_dir_all(&dir).unwrap();
+}
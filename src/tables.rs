@@ -0,0 +1,273 @@
+use crate::{
+    storage, write_db, Armor, Item, Skill, Weapon, ARMOR_DB, ITEM_DB, SKILL_DB, WEAPON_DB,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::fmt;
+use thiserror::Error;
+
+#[derive(Debug)]
+pub struct RowError {
+    pub line: u64,
+    pub message: String,
+}
+
+impl fmt::Display for RowError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "rad {}: {}", self.line, self.message)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum TableError {
+    #[error("unknown table: {0} (weapons, armor, items or skills)")]
+    UnknownTable(String),
+    #[error("wrong columns, missing: [{}], unknown: [{}]", missing.join(", "), unknown.join(", "))]
+    Columns {
+        missing: Vec<String>,
+        unknown: Vec<String>,
+    },
+    #[error("invalid rows, nothing was imported ({}):\n{}", .0.len(), .0.iter().map(|e| e.to_string()).collect::<Vec<_>>().join("\n"))]
+    Rows(Vec<RowError>),
+    #[error("error reading the CSV: {0}")]
+    Csv(#[from] csv::Error),
+    #[error(transparent)]
+    Db(#[from] crate::Error),
+}
+
+pub trait Keyed {
+    fn id(&self) -> usize;
+}
+
+impl Keyed for Weapon {
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+impl Keyed for Armor {
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+impl Keyed for Item {
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+impl Keyed for Skill {
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum DataTable {
+    Weapons,
+    Armor,
+    Items,
+    Skills,
+}
+
+//Rows changed and rows added by an import
+pub struct ImportSummary {
+    pub updated: usize,
+    pub added: usize,
+}
+
+impl DataTable {
+    pub fn parse(name: &str) -> Result<DataTable, TableError> {
+        match name {
+            "weapons" | "weapon" => Ok(DataTable::Weapons),
+            "armor" => Ok(DataTable::Armor),
+            "items" | "item" => Ok(DataTable::Items),
+            "skills" | "skill" => Ok(DataTable::Skills),
+            other => Err(TableError::UnknownTable(other.to_string())),
+        }
+    }
+
+    //Column names, the same as the struct fields and the JSON keys
+    pub fn columns(&self) -> &'static [&'static str] {
+        match self {
+            DataTable::Weapons => &[
+                "id",
+                "namn",
+                "bonus",
+                "init",
+                "skada",
+                "krit",
+                "räckvidd",
+                "övrigt",
+                "kostnad",
+            ],
+            DataTable::Armor => &["id", "name", "rating", "addons", "tech", "comment"],
+            DataTable::Items => &["id", "name", "description", "cost", "weight"],
+            DataTable::Skills => &["id", "name", "description", "category"],
+        }
+    }
+
+    //Both directions work on the core rules as stored, without homebrew overlays
+    pub fn export(&self) -> Result<String, TableError> {
+        match self {
+            DataTable::Weapons => to_csv(&storage::load::<Vec<Weapon>>(WEAPON_DB)?),
+            DataTable::Armor => to_csv(&storage::load::<Vec<Armor>>(ARMOR_DB)?),
+            DataTable::Items => to_csv(&storage::load::<Vec<Item>>(ITEM_DB)?),
+            DataTable::Skills => to_csv(&storage::load::<Vec<Skill>>(SKILL_DB)?),
+        }
+    }
+
+    //Validates every row before anything is written, so a bad file leaves the table untouched
+    pub fn import(&self, data: &str, dry_run: bool) -> Result<ImportSummary, TableError> {
+        let columns = self.columns();
+        match self {
+            DataTable::Weapons => import_into::<Weapon>(WEAPON_DB, data, columns, dry_run),
+            DataTable::Armor => import_into::<Armor>(ARMOR_DB, data, columns, dry_run),
+            DataTable::Items => import_into::<Item>(ITEM_DB, data, columns, dry_run),
+            DataTable::Skills => import_into::<Skill>(SKILL_DB, data, columns, dry_run),
+        }
+    }
+}
+
+fn to_csv<T: Serialize>(rows: &[T]) -> Result<String, TableError> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for row in rows {
+        writer.serialize(row)?;
+    }
+    let bytes = writer
+        .into_inner()
+        .map_err(|e| TableError::Csv(e.into_error().into()))?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+fn from_csv<T: DeserializeOwned + Keyed>(
+    data: &str,
+    columns: &[&str],
+) -> Result<Vec<T>, TableError> {
+    let mut reader = csv::Reader::from_reader(data.as_bytes());
+    let headers = reader.headers()?.clone();
+    let missing: Vec<String> = columns
+        .iter()
+        .filter(|c| !headers.iter().any(|h| h == **c))
+        .map(|c| c.to_string())
+        .collect();
+    let unknown: Vec<String> = headers
+        .iter()
+        .filter(|h| !columns.contains(h))
+        .map(|h| h.to_string())
+        .collect();
+    if !missing.is_empty() || !unknown.is_empty() {
+        return Err(TableError::Columns { missing, unknown });
+    }
+
+    let mut rows: Vec<T> = Vec::new();
+    let mut errors = Vec::new();
+    for record in reader.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                let line = e.position().map_or(0, |p| p.line());
+                errors.push(RowError {
+                    line,
+                    message: e.to_string(),
+                });
+                continue;
+            }
+        };
+        let line = record.position().map_or(0, |p| p.line());
+        match record.deserialize::<T>(Some(&headers)) {
+            Ok(row) if rows.iter().any(|r| r.id() == row.id()) => errors.push(RowError {
+                line,
+                message: format!("id {} finns redan i filen", row.id()),
+            }),
+            Ok(row) => rows.push(row),
+            Err(e) => {
+                //Name the offending column instead of the raw field index
+                let message = match e.kind() {
+                    csv::ErrorKind::Deserialize { err, .. } => {
+                        let column = err
+                            .field()
+                            .and_then(|i| headers.get(i as usize))
+                            .unwrap_or("?");
+                        format!("kolumn {}: {}", column, err.kind())
+                    }
+                    _ => e.to_string(),
+                };
+                errors.push(RowError { line, message });
+            }
+        }
+    }
+    if !errors.is_empty() {
+        return Err(TableError::Rows(errors));
+    }
+    Ok(rows)
+}
+
+//Replaces rows with a matching id and appends new ids, other rows keep their place
+fn merge<T: Keyed>(existing: &mut Vec<T>, rows: Vec<T>) -> ImportSummary {
+    let mut summary = ImportSummary {
+        updated: 0,
+        added: 0,
+    };
+    for row in rows {
+        match existing.iter().position(|e| e.id() == row.id()) {
+            Some(i) => {
+                existing[i] = row;
+                summary.updated += 1;
+            }
+            None => {
+                existing.push(row);
+                summary.added += 1;
+            }
+        }
+    }
+    summary
+}
+
+fn import_into<T: Serialize + DeserializeOwned + Keyed>(
+    path: &str,
+    data: &str,
+    columns: &[&str],
+    dry_run: bool,
+) -> Result<ImportSummary, TableError> {
+    let rows = from_csv(data, columns)?;
+    let mut existing: Vec<T> = storage::load(path)?;
+    let summary = merge(&mut existing, rows);
+    if !dry_run {
+        write_db(path, &existing)?;
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+#[test]
+fn test_csv_round_trip() {
+    let weapons = crate::read_weapon_db().unwrap();
+    let csv = to_csv(&weapons).unwrap();
+    let header = DataTable::Weapons.columns().join(",");
+    assert!(csv.starts_with(&header));
+    let mut existing = weapons.clone();
+    let summary = merge(
+        &mut existing,
+        from_csv::<Weapon>(&csv, DataTable::Weapons.columns()).unwrap(),
+    );
+    assert_eq!((summary.updated, summary.added), (weapons.len(), 0));
+
+    let bad = format!(
+        "{}\n1,Kniv,x,0,2,3,Nära,,100\n1,Kniv,1,0,2,3,Nära,,100\n1,Kniv,1,0,2,3,Nära,,100\n",
+        header
+    );
+    match from_csv::<Weapon>(&bad, DataTable::Weapons.columns()) {
+        Err(TableError::Rows(errors)) => {
+            assert_eq!(errors.len(), 2);
+            assert_eq!(errors[0].line, 2);
+            assert!(errors[0].message.starts_with("kolumn bonus"));
+            assert_eq!(errors[1].line, 4);
+        }
+        _ => panic!("expected row errors"),
+    }
+    assert!(matches!(
+        from_csv::<Item>("id,namn\n1,x\n", DataTable::Items.columns()),
+        Err(TableError::Columns { .. })
+    ));
+}
@@ -0,0 +1,442 @@
+use crate::{Character, Skill};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use tui::{
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, BorderType, Borders, List, ListItem, Paragraph, Wrap},
+};
+
+//Group talent that halves the travel time of a journey
+pub const FASTEST_ROUTE: &str = "Snabbaste rutten";
+//Width and height of the ASCII map, system coordinates must fit inside
+pub const MAP_WIDTH: usize = 60;
+pub const MAP_HEIGHT: usize = 21;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct StarSystem {
+    pub id: usize,
+    pub name: String,
+    pub x: usize,
+    pub y: usize,
+    pub description: String,
+    pub hazards: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Portal {
+    pub from: usize,
+    pub to: usize,
+    pub days: u32,
+    #[serde(default)]
+    pub hazard: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Horizon {
+    pub systems: Vec<StarSystem>,
+    pub portals: Vec<Portal>,
+}
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum RouteMetric {
+    Days,
+    Jumps,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Route {
+    //System ids from start to destination
+    pub systems: Vec<usize>,
+    pub days: u32,
+}
+
+impl Route {
+    pub fn jumps(&self) -> usize {
+        self.systems.len().saturating_sub(1)
+    }
+}
+
+impl Horizon {
+    pub fn system(&self, id: usize) -> Option<&StarSystem> {
+        self.systems.iter().find(|s| s.id == id)
+    }
+
+    //Portals are traversable in both directions
+    pub fn portal(&self, a: usize, b: usize) -> Option<&Portal> {
+        self.portals
+            .iter()
+            .find(|p| (p.from == a && p.to == b) || (p.from == b && p.to == a))
+    }
+
+    fn neighbours(&self, id: usize) -> Vec<(usize, u32)> {
+        self.portals
+            .iter()
+            .filter_map(|p| {
+                if p.from == id {
+                    Some((p.to, p.days))
+                } else if p.to == id {
+                    Some((p.from, p.days))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    //Dijkstra over the portal network, weighted by days or number of jumps
+    pub fn plan_route(&self, from: usize, to: usize, metric: RouteMetric) -> Option<Route> {
+        let mut best: Vec<Option<u32>> = vec![None; self.systems.len()];
+        let mut previous: Vec<Option<usize>> = vec![None; self.systems.len()];
+        let index = |id: usize| self.systems.iter().position(|s| s.id == id);
+        let start = index(from)?;
+        index(to)?;
+        best[start] = Some(0);
+        let mut queue = BinaryHeap::new();
+        queue.push(Reverse((0u32, 0u32, from)));
+        while let Some(Reverse((cost, days, id))) = queue.pop() {
+            if id == to {
+                let mut systems = vec![to];
+                let mut current = index(to)?;
+                while let Some(prev) = previous[current] {
+                    systems.push(self.systems[prev].id);
+                    current = prev;
+                }
+                systems.reverse();
+                return Some(Route { systems, days });
+            }
+            let i = index(id)?;
+            if let Some(known) = best[i] {
+                if cost > known {
+                    continue;
+                }
+            }
+            for (next, leg_days) in self.neighbours(id) {
+                let j = match index(next) {
+                    Some(j) => j,
+                    None => continue,
+                };
+                let next_cost = match metric {
+                    RouteMetric::Days => cost + leg_days,
+                    RouteMetric::Jumps => cost + 1,
+                };
+                let better = match best[j] {
+                    Some(known) => next_cost < known,
+                    None => true,
+                };
+                if better {
+                    best[j] = Some(next_cost);
+                    previous[j] = Some(i);
+                    queue.push(Reverse((next_cost, days + leg_days, next)));
+                }
+            }
+        }
+        None
+    }
+}
+
+//True if any character holds the named talent
+pub fn party_has_talent(characters: &[Character], skills: &[Skill], talent: &str) -> bool {
+    skills
+        .iter()
+        .filter(|s| s.name.eq_ignore_ascii_case(talent))
+        .any(|s| characters.iter().any(|c| c.skill_ids.contains(&s.id)))
+}
+
+//Travel days for the party, "Snabbaste rutten" halves the time (rounded up)
+pub fn party_travel_days(days: u32, characters: &[Character], skills: &[Skill]) -> u32 {
+    if party_has_talent(characters, skills, FASTEST_ROUTE) {
+        days.div_ceil(2)
+    } else {
+        days
+    }
+}
+
+//Rolls one d6 per leg, a six means the party runs into one of the system or portal hazards
+pub fn roll_route_encounters<R: Rng>(rng: &mut R, horizon: &Horizon, route: &Route) -> Vec<String> {
+    let mut encounters = Vec::new();
+    for leg in route.systems.windows(2) {
+        if rng.gen_range(1..=6) < 6 {
+            continue;
+        }
+        let system = match horizon.system(leg[1]) {
+            Some(system) => system,
+            None => continue,
+        };
+        let mut hazards = system.hazards.clone();
+        if let Some(hazard) = horizon
+            .portal(leg[0], leg[1])
+            .and_then(|p| p.hazard.clone())
+        {
+            hazards.push(hazard);
+        }
+        if hazards.is_empty() {
+            continue;
+        }
+        let hazard = &hazards[rng.gen_range(0..hazards.len())];
+        encounters.push(format!("{}: {}", system.name, hazard));
+    }
+    encounters
+}
+
+pub struct TravelPlanner {
+    pub from: usize,
+    pub to: Option<usize>,
+    pub metric: RouteMetric,
+    pub route: Option<Route>,
+    pub encounters: Vec<String>,
+}
+
+impl TravelPlanner {
+    pub fn new(from: usize) -> TravelPlanner {
+        TravelPlanner {
+            from,
+            to: None,
+            metric: RouteMetric::Days,
+            route: None,
+            encounters: Vec::new(),
+        }
+    }
+
+    pub fn toggle_metric(&mut self) {
+        self.metric = match self.metric {
+            RouteMetric::Days => RouteMetric::Jumps,
+            RouteMetric::Jumps => RouteMetric::Days,
+        };
+    }
+
+    pub fn plan<R: Rng>(&mut self, rng: &mut R, horizon: &Horizon) {
+        self.route = self
+            .to
+            .and_then(|to| horizon.plan_route(self.from, to, self.metric));
+        self.encounters = match &self.route {
+            Some(route) => roll_route_encounters(rng, horizon, route),
+            None => Vec::new(),
+        };
+    }
+}
+
+fn draw_line(
+    grid: &mut [Vec<(char, Style)>],
+    a: (usize, usize),
+    b: (usize, usize),
+    c: char,
+    style: Style,
+) {
+    let (mut x0, mut y0) = (a.0 as i32, a.1 as i32);
+    let (x1, y1) = (b.0 as i32, b.1 as i32);
+    let dx = (x1 - x0).abs();
+    let dy = -(y1 - y0).abs();
+    let sx = if x0 < x1 { 1 } else { -1 };
+    let sy = if y0 < y1 { 1 } else { -1 };
+    let mut err = dx + dy;
+    loop {
+        if let Some(cell) = grid
+            .get_mut(y0 as usize)
+            .and_then(|row| row.get_mut(x0 as usize))
+        {
+            *cell = (c, style);
+        }
+        if x0 == x1 && y0 == y1 {
+            break;
+        }
+        let e2 = 2 * err;
+        if e2 >= dy {
+            err += dy;
+            x0 += sx;
+        }
+        if e2 <= dx {
+            err += dx;
+            y0 += sy;
+        }
+    }
+}
+
+//Plots the systems and portals on a character grid, the route is highlighted
+pub fn render_map<'a>(horizon: &Horizon, planner: &TravelPlanner) -> Paragraph<'a> {
+    let mut grid = vec![vec![(' ', Style::default()); MAP_WIDTH]; MAP_HEIGHT];
+    let portal_style = Style::default().fg(Color::DarkGray);
+    let route_style = Style::default().fg(Color::Yellow);
+    for portal in horizon.portals.iter() {
+        if let (Some(a), Some(b)) = (horizon.system(portal.from), horizon.system(portal.to)) {
+            draw_line(&mut grid, (a.x, a.y), (b.x, b.y), '·', portal_style);
+        }
+    }
+    if let Some(route) = &planner.route {
+        for leg in route.systems.windows(2) {
+            if let (Some(a), Some(b)) = (horizon.system(leg[0]), horizon.system(leg[1])) {
+                draw_line(&mut grid, (a.x, a.y), (b.x, b.y), '*', route_style);
+            }
+        }
+    }
+    for system in horizon.systems.iter() {
+        let on_route = planner
+            .route
+            .as_ref()
+            .is_some_and(|r| r.systems.contains(&system.id));
+        let style = if system.id == planner.from || Some(system.id) == planner.to {
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD)
+        } else if on_route {
+            route_style.add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::White)
+        };
+        let label: String = system.name.chars().take(3).collect();
+        if let Some(row) = grid.get_mut(system.y) {
+            for (i, c) in std::iter::once('o').chain(label.chars()).enumerate() {
+                if let Some(cell) = row.get_mut(system.x + i) {
+                    *cell = (c, style);
+                }
+            }
+        }
+    }
+    let lines: Vec<Spans> = grid
+        .into_iter()
+        .map(|row| {
+            Spans::from(
+                row.into_iter()
+                    .map(|(c, style)| Span::styled(c.to_string(), style))
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .collect();
+    Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::White))
+            .title("Tredje horisonten")
+            .border_type(BorderType::Plain),
+    )
+}
+
+pub fn render_travel<'a>(
+    horizon: &Horizon,
+    planner: &TravelPlanner,
+    characters: &[Character],
+    skills: &[Skill],
+) -> (List<'a>, Paragraph<'a>) {
+    let items: Vec<_> = horizon
+        .systems
+        .iter()
+        .map(|system| {
+            let marker = if system.id == planner.from {
+                "> "
+            } else if Some(system.id) == planner.to {
+                "* "
+            } else {
+                "  "
+            };
+            ListItem::new(Spans::from(vec![
+                Span::styled(marker, Style::default().fg(Color::Cyan)),
+                Span::raw(system.name.clone()),
+            ]))
+        })
+        .collect();
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default().fg(Color::White))
+                .title("System")
+                .border_type(BorderType::Plain),
+        )
+        .highlight_style(
+            Style::default()
+                .bg(Color::Yellow)
+                .fg(Color::Black)
+                .add_modifier(Modifier::BOLD),
+        );
+
+    let metric = match planner.metric {
+        RouteMetric::Days => "kortast restid",
+        RouteMetric::Jumps => "minst antal hopp",
+    };
+    let mut lines = vec![Spans::from(format!("Rutt: {}", metric)), Spans::from("")];
+    match &planner.route {
+        Some(route) => {
+            for leg in route.systems.windows(2) {
+                let name = |id| horizon.system(id).map_or("?", |s| s.name.as_str());
+                let days = horizon.portal(leg[0], leg[1]).map_or(0, |p| p.days);
+                lines.push(Spans::from(format!(
+                    "{} -> {} ({} dagar)",
+                    name(leg[0]),
+                    name(leg[1]),
+                    days
+                )));
+            }
+            let party_days = party_travel_days(route.days, characters, skills);
+            lines.push(Spans::from(""));
+            lines.push(Spans::from(format!(
+                "{} hopp, {} dagar",
+                route.jumps(),
+                route.days
+            )));
+            if party_days != route.days {
+                lines.push(Spans::from(format!(
+                    "{}: {} dagar",
+                    FASTEST_ROUTE, party_days
+                )));
+            }
+            lines.push(Spans::from(""));
+            lines.push(Spans::from(Span::styled(
+                "Möten",
+                Style::default().add_modifier(Modifier::BOLD),
+            )));
+            if planner.encounters.is_empty() {
+                lines.push(Spans::from("Inga möten"));
+            }
+            for encounter in planner.encounters.iter() {
+                lines.push(Spans::from(encounter.clone()));
+            }
+        }
+        None => lines.push(Spans::from("Välj destination med Enter")),
+    }
+    let route_detail = Paragraph::new(lines).wrap(Wrap { trim: true }).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::White))
+            .title("Resplan")
+            .border_type(BorderType::Plain),
+    );
+
+    (list, route_detail)
+}
+
+#[cfg(test)]
+#[test]
+fn test_plan_route() {
+    let system = |id| StarSystem {
+        id,
+        name: format!("S{}", id),
+        x: id,
+        y: id,
+        description: String::new(),
+        hazards: vec![],
+    };
+    let portal = |from, to, days| Portal {
+        from,
+        to,
+        days,
+        hazard: None,
+    };
+    let horizon = Horizon {
+        systems: vec![system(1), system(2), system(3), system(4)],
+        portals: vec![
+            portal(1, 2, 2),
+            portal(2, 3, 2),
+            portal(3, 4, 2),
+            portal(1, 4, 10),
+        ],
+    };
+    let by_days = horizon.plan_route(1, 4, RouteMetric::Days).unwrap();
+    assert_eq!(by_days.systems, vec![1, 2, 3, 4]);
+    assert_eq!(by_days.days, 6);
+    let by_jumps = horizon.plan_route(4, 1, RouteMetric::Jumps).unwrap();
+    assert_eq!(by_jumps.systems, vec![4, 1]);
+    assert_eq!(by_jumps.jumps(), 1);
+    assert_eq!(horizon.plan_route(1, 9, RouteMetric::Days), None);
+}